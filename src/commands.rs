@@ -3,37 +3,74 @@
 extern crate libc;
 
 use crate::config::Config;
+use crate::ewmh;
 use crate::layout::{Layout, LayoutMsg};
 use crate::xlib_window_system::XlibWindowSystem;
 use crate::state::WmState;
-use crate::workspace::MoveOp;
-use crate::utils::exec;
+use crate::workspace::{CycleMode, MoveOp};
+use crate::utils::{self, exec};
 use std::process::Child;
 use x11::xlib::Window;
 use anyhow::{Context, Result};
 
 type CustomCmdFn = dyn Fn(&WmState) -> Result<Option<Cmd>, String>;
 
+// what to do with the line picked in a menu once the menu program exited
+pub enum MenuAction {
+    FocusWindow(Vec<Window>),
+    BringWindow(Vec<Window>),
+    AddWorkspace,
+    RenameWorkspace(usize),
+}
+
 pub enum Cmd {
     Custom(Box<CustomCmdFn>),
     Exec(String, Vec<String>),
     SpawnTerminal(Vec<String>),
+    ToggleScratchpad(String),
+    RunOrRaise(String, String, Vec<String>),
+    SelectWindow,
+    BringWindow,
     SwitchWorkspace(usize),
+    SwitchToPreviousWorkspace,
+    SwitchNextWorkspace(CycleMode),
+    SwitchPrevWorkspace(CycleMode),
+    MoveToNextWorkspace(CycleMode),
+    MoveToPrevWorkspace(CycleMode),
+    AddWorkspace(Option<String>),
+    RenameWorkspace(Option<String>),
+    RemoveWorkspace,
+    MoveWorkspace(usize),
     SwitchScreen(usize),
     MoveToWorkspace(usize),
+    CopyToWorkspace(usize),
+    CopyToAllWorkspaces,
     MoveToScreen(usize),
     SendLayoutMsg(LayoutMsg),
     NestLayout(Box<dyn Fn() -> Box<dyn Layout>>),
     Reload(Vec<(String,String)>),
+    Mark(String),
+    FocusMark(String),
+    SwapWithMark(String),
+    MoveToMark(String),
     RemoveNested,
     Exit,
     KillClient,
+    ForceKill,
+    KillCopy,
+    Minimize,
+    RestoreLastMinimized,
+    SelectMinimized,
+    ToggleShowDesktop,
     FocusParentUp,
     FocusParentDown,
     FocusParentMaster,
     FocusUp,
     FocusDown,
     FocusMaster,
+    FocusPrevious,
+    FocusHistoryBack,
+    FocusHistoryForward,
     SwapUp,
     SwapDown,
     SwapMaster,
@@ -55,15 +92,102 @@ impl Cmd {
             }
             Cmd::Exec(cmd, args) => {
                 debug!("Cmd::Exec: {} {:?}", cmd, args);
-                exec(cmd.clone(), args.clone());
+                spawn(state, cmd.clone(), args.clone());
             }
             Cmd::SpawnTerminal(args) => {
                 debug!("Cmd::SpawnTerminal: {} {:?}", config.terminal, args);
-                exec(config.terminal.clone(), args.clone());
+                spawn(state, config.terminal.clone(), args.clone());
+            }
+            Cmd::ToggleScratchpad(name) => {
+                debug!("Cmd::ToggleScratchpad: {}", name);
+                if !state.toggle_scratchpad(xws, config, name) {
+                    match config.scratchpads.iter().find(|x| x.name == *name) {
+                        Some(scratchpad) => {
+                            exec(scratchpad.cmd.clone(), scratchpad.args.clone());
+                        }
+                        None => warn!("unknown scratchpad: {}", name),
+                    }
+                }
+            }
+            Cmd::RunOrRaise(class, cmd, args) => {
+                debug!("Cmd::RunOrRaise: {} {} {:?}", class, cmd, args);
+                let windows = state.find_windows_by_class(xws, class);
+
+                // cycle through all matching windows on repeated calls
+                let window = state.current_ws()
+                    .focused_window()
+                    .and_then(|w| windows.iter().position(|&x| x == w))
+                    .map(|idx| windows[(idx + 1) % windows.len()])
+                    .or_else(|| windows.first().copied());
+
+                match window {
+                    Some(window) => state.focus_window(xws, config, window, true),
+                    None => spawn(state, cmd.clone(), args.clone()),
+                }
+            }
+            Cmd::SelectWindow => {
+                debug!("Cmd::SelectWindow");
+                select_window(xws, state, config, state.all_windows(), MenuAction::FocusWindow)?;
+            }
+            Cmd::BringWindow => {
+                debug!("Cmd::BringWindow");
+                select_window(xws, state, config, state.all_windows(), MenuAction::BringWindow)?;
             }
             Cmd::SwitchWorkspace(index) => {
                 debug!("Cmd::SwitchWorkspace: {}", index);
-                state.switch_to_ws(xws, config, index - 1, true);
+                if config.auto_back_and_forth && index - 1 == state.get_ws_index() {
+                    state.switch_to_prev_ws(xws, config);
+                } else {
+                    state.switch_to_ws(xws, config, index - 1, true);
+                }
+            }
+            Cmd::SwitchToPreviousWorkspace => {
+                debug!("Cmd::SwitchToPreviousWorkspace");
+                state.switch_to_prev_ws(xws, config);
+            }
+            Cmd::SwitchNextWorkspace(mode) | Cmd::SwitchPrevWorkspace(mode) => {
+                let forward = matches!(self, Cmd::SwitchNextWorkspace(_));
+                debug!("Cmd::Switch{}Workspace: {:?}", if forward { "Next" } else { "Prev" }, mode);
+
+                if let Some(index) = state.find_relative_ws(*mode, forward) {
+                    state.switch_to_ws(xws, config, index, true);
+                }
+            }
+            Cmd::MoveToNextWorkspace(mode) | Cmd::MoveToPrevWorkspace(mode) => {
+                let forward = matches!(self, Cmd::MoveToNextWorkspace(_));
+                debug!("Cmd::MoveTo{}Workspace: {:?}", if forward { "Next" } else { "Prev" }, mode);
+
+                if let Some(index) = state.find_relative_ws(*mode, forward) {
+                    state.move_window_to_ws(xws, config, index);
+                    state.switch_to_ws(xws, config, index, true);
+                }
+            }
+            Cmd::AddWorkspace(tag) => {
+                debug!("Cmd::AddWorkspace: {:?}", tag);
+                match tag {
+                    Some(tag) => add_workspace(xws, state, config, tag),
+                    None => prompt(state, config, "tag", MenuAction::AddWorkspace)?,
+                }
+            }
+            Cmd::RenameWorkspace(tag) => {
+                debug!("Cmd::RenameWorkspace: {:?}", tag);
+                let index = state.get_ws_index();
+                match tag {
+                    Some(tag) => state.rename_ws(xws, index, tag),
+                    None => prompt(state, config, "tag", MenuAction::RenameWorkspace(index))?,
+                }
+            }
+            Cmd::RemoveWorkspace => {
+                debug!("Cmd::RemoveWorkspace");
+                let index = state.get_ws_index();
+                if !state.remove_ws(xws, config, index) {
+                    warn!("only empty workspaces can be removed");
+                }
+            }
+            Cmd::MoveWorkspace(index) => {
+                debug!("Cmd::MoveWorkspace: {}", index);
+                let from = state.get_ws_index();
+                state.move_ws(xws, from, index - 1);
             }
             Cmd::SwitchScreen(screen) => {
                 debug!("Cmd::SwitchScreen: {}", screen);
@@ -73,6 +197,18 @@ impl Cmd {
                 debug!("Cmd::MoveToWorkspace: {}", index);
                 state.move_window_to_ws(xws, config, index - 1);
             }
+            Cmd::CopyToWorkspace(index) => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::CopyToWorkspace: {} {:#x}", index, window);
+                    state.copy_window_to_ws(xws, config, window, index - 1);
+                }
+            }
+            Cmd::CopyToAllWorkspaces => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::CopyToAllWorkspaces: {:#x}", window);
+                    state.copy_window_to_all(xws, config, window);
+                }
+            }
             Cmd::MoveToScreen(screen) => {
                 debug!("Cmd::MoveToScreen: {}", screen);
                 state.move_window_to_screen(xws, config, screen - 1);
@@ -102,6 +238,29 @@ impl Cmd {
                 #[cfg(not(feature = "reload"))]
                 warn!("missing reload support. Recompile with the reload feature enabled");
             }
+            Cmd::Mark(mark) => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::Mark: {} {:#x}", mark, window);
+                    state.mark_window(window, mark);
+                }
+            }
+            Cmd::FocusMark(mark) => {
+                debug!("Cmd::FocusMark: {}", mark);
+                if let Some(window) = state.find_mark(mark) {
+                    state.focus_window(xws, config, window, true);
+                }
+            }
+            Cmd::SwapWithMark(mark) => {
+                debug!("Cmd::SwapWithMark: {}", mark);
+                if let (Some(window), Some(other)) = (state.current_ws().focused_window(), state.find_mark(mark)) {
+                    state.swap_windows(xws, config, window, other);
+                    xws.skip_enter_events();
+                }
+            }
+            Cmd::MoveToMark(mark) => {
+                debug!("Cmd::MoveToMark: {}", mark);
+                state.move_window_to_mark(xws, config, mark);
+            }
             Cmd::Exit => {
                 debug!("Cmd::Exit");
                 xws.close();
@@ -109,11 +268,67 @@ impl Cmd {
             Cmd::KillClient => {
                 if let Some(window) = state.current_ws().focused_window() {
                     debug!("Cmd::KillClient: {:#x}", window);
-                    xws.kill_window(window);
+
+                    // asking politely again is pointless once the client stopped answering pings
+                    if state.is_hung(window) {
+                        force_kill(xws, window);
+                    } else {
+                        xws.kill_window(window);
+                        state.ping_window(xws, window);
+                    }
+                }
+            }
+            Cmd::ForceKill => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::ForceKill: {:#x}", window);
+                    force_kill(xws, window);
+                }
+            }
+            Cmd::KillCopy => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::KillCopy: {:#x}", window);
+
+                    // the last copy closes the window
+                    if !state.remove_copy(xws, config, window) {
+                        xws.kill_window(window);
+                    }
+                }
+            }
+            Cmd::Minimize => {
+                if let Some(window) = state.current_ws().focused_window() {
+                    debug!("Cmd::Minimize: {:#x}", window);
+                    state.minimize_window(xws, config, window);
+                }
+            }
+            Cmd::RestoreLastMinimized => {
+                if let Some(&window) = state.current_ws().minimized_windows().last() {
+                    debug!("Cmd::RestoreLastMinimized: {:#x}", window);
+                    state.focus_window(xws, config, window, false);
                 }
             }
+            Cmd::SelectMinimized => {
+                debug!("Cmd::SelectMinimized");
+                select_window(xws, state, config, state.minimized_windows(), MenuAction::FocusWindow)?;
+            }
+            Cmd::ToggleShowDesktop => {
+                debug!("Cmd::ToggleShowDesktop");
+                state.show_desktop(xws, config, !state.is_showing_desktop());
+            }
+            Cmd::FocusPrevious => {
+                debug!("Cmd::FocusPrevious");
+                state.focus_previous(xws, config);
+            }
+            Cmd::FocusHistoryBack => {
+                debug!("Cmd::FocusHistoryBack");
+                state.cycle_focus_history(xws, config, false);
+            }
+            Cmd::FocusHistoryForward => {
+                debug!("Cmd::FocusHistoryForward");
+                state.cycle_focus_history(xws, config, true);
+            }
             Cmd::FocusUp | Cmd::FocusDown | Cmd::FocusMaster | Cmd::FocusParentUp | Cmd::FocusParentDown | Cmd::FocusParentMaster => {
                 if let Some(window) = state.current_ws().focused_window() {
+                    let hidden = state.current_ws().managed.hidden_windows();
                     let workspace = state.current_ws_mut();
                     let new_focus = match self {
                         Cmd::FocusUp => {
@@ -144,6 +359,9 @@ impl Cmd {
                     };
 
                     if let Some(window) = new_focus {
+                        if hidden.contains(&window) {
+                            state.redraw_current(xws, config);
+                        }
                         xws.focus_window(window);
                     }
                 }
@@ -190,6 +408,130 @@ impl Cmd {
     }
 }
 
+// runs the command from the project directory of the current workspace if it has one
+// and remembers the workspace to place the first window of the process on
+fn spawn(state: &mut WmState, cmd: String, args: Vec<String>) {
+    let pid = match state.current_ws().get_project() {
+        Some(project) => utils::exec_in(cmd, args, Some(project.dir.clone()), project.envs.clone()),
+        None => exec(cmd, args),
+    };
+
+    if let Some(pid) = pid {
+        let index = state.get_ws_index();
+        state.add_spawn(pid, index);
+    }
+}
+
+fn add_workspace(xws: &XlibWindowSystem, state: &mut WmState, config: &Config, tag: &str) {
+    let index = state.add_ws(xws, tag, (config.default_layout)());
+    state.switch_to_ws(xws, config, index, true);
+}
+
+// starts the configured menu program, the action runs in finish_menu once a line was picked
+fn open_menu(state: &mut WmState, config: &Config, args: &[String], lines: &[String], action: MenuAction) -> Result<()> {
+    if state.has_menu() {
+        warn!("a menu is already open");
+        return Ok(());
+    }
+
+    let stream = utils::menu(&config.menu, args, lines)
+        .context("failed to run menu")?;
+    state.set_menu(stream, action);
+    Ok(())
+}
+
+// called by the event loop when the menu program exited
+pub fn finish_menu(xws: &XlibWindowSystem, state: &mut WmState, config: &Config) -> Result<()> {
+    let Some((stream, action)) = state.take_menu() else {
+        return Ok(());
+    };
+
+    let Some(selection) = utils::menu_selection(stream)? else {
+        return Ok(());
+    };
+
+    // every window line starts with its index so duplicate titles still map to the right window
+    let selected_window = |windows: &[Window]| selection
+        .split_once(':')
+        .and_then(|(idx, _)| idx.trim().parse::<usize>().ok())
+        .and_then(|idx| windows.get(idx).copied())
+        .filter(|&w| state.contains(w));
+
+    match action {
+        MenuAction::FocusWindow(windows) => {
+            if let Some(window) = selected_window(&windows) {
+                state.focus_window(xws, config, window, true);
+            }
+        }
+        MenuAction::BringWindow(windows) => {
+            if let Some(window) = selected_window(&windows) {
+                let index = state.get_ws_index();
+                state.send_window_to_ws(xws, config, window, index);
+                state.focus_window(xws, config, window, false);
+            }
+        }
+        MenuAction::AddWorkspace => add_workspace(xws, state, config, &selection),
+        MenuAction::RenameWorkspace(index) => state.rename_ws(xws, index, &selection),
+    }
+
+    Ok(())
+}
+
+// asks the user for a line of text with the configured menu program
+fn prompt(state: &mut WmState, config: &Config, name: &str, action: MenuAction) -> Result<()> {
+    let mut args = config.menu_args.clone();
+    args.extend(["-p".to_string(), name.to_string()]);
+
+    open_menu(state, config, &args, &[], action)
+}
+
+// hung clients do not react to a closed X connection, local ones get a SIGKILL instead
+fn force_kill(xws: &XlibWindowSystem, window: Window) {
+    let is_local = xws
+        .get_string_list(window, "WM_CLIENT_MACHINE")
+        .first()
+        .is_some_and(|host| Some(host) == utils::hostname().as_ref());
+
+    // the pid comes from the client, never signal ourselves, a process group or everything
+    let pid = ewmh::get_wm_pid(xws, window)
+        .filter(|_| is_local)
+        .filter(|&pid| pid > 1 && pid <= i32::MAX as u64 && pid != std::process::id() as u64);
+
+    match pid {
+        Some(pid) => {
+            debug!("sending SIGKILL to {}", pid);
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+        None => xws.kill_client(window),
+    }
+}
+
+// lets the user pick one of the given windows with the configured menu program
+fn select_window(xws: &XlibWindowSystem, state: &mut WmState, config: &Config, windows: Vec<Window>, action: fn(Vec<Window>) -> MenuAction) -> Result<()> {
+    let windows: Vec<Window> = windows
+        .into_iter()
+        .filter(|&w| !ewmh::is_skipped(xws, w))
+        .collect();
+
+    let lines: Vec<String> = windows
+        .iter()
+        .enumerate()
+        .map(|(idx, &w)| {
+            let tag = state.find_window(w)
+                .and_then(|idx| state.get_ws(idx))
+                .map(|ws| ws.get_tag().to_string())
+                .unwrap_or_default();
+
+            let line = format!("{}: [{}] {} - {}", idx, tag, xws.get_class_name(w).unwrap_or_default(), xws.get_window_title(w));
+            line.replace(['\n', '\r'], " ")
+        })
+        .collect();
+
+    open_menu(state, config, &config.menu_args, &lines, action(windows))
+}
+
 #[cfg(feature = "reload")]
 fn reload(config: &Config, state: &WmState, mut bar_handle: Option<&mut Child>, custom_envs: &[(String, String)]) -> Result<()> {
     use std::{env, iter};
@@ -271,6 +613,27 @@ fn reload(config: &Config, state: &WmState, mut bar_handle: Option<&mut Child>,
     Ok(())
 }
 
+pub enum ScratchpadRule {
+    Class(String),
+    Title(String),
+}
+
+impl ScratchpadRule {
+    pub fn matches(&self, xws: &XlibWindowSystem, window: Window) -> bool {
+        match self {
+            ScratchpadRule::Class(class) => xws.get_class_name(window).as_ref() == Some(class),
+            ScratchpadRule::Title(title) => xws.get_window_title(window) == *title,
+        }
+    }
+}
+
+pub struct Scratchpad {
+    pub name: String,
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub rule: ScratchpadRule,
+}
+
 pub struct ManageHook {
     pub class_name: String,
     pub cmd: CmdManage,
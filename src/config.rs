@@ -1,10 +1,11 @@
 #![allow(unused)]
 
 use crate::keycode::*;
-use crate::workspace::WorkspaceConfig;
+use crate::workspace::{CycleMode, WorkspaceConfig};
 use crate::state::WmState;
 use crate::xlib_window_system::XlibWindowSystem;
-use crate::commands::{Cmd, ManageHook};
+use crate::commands::{Cmd, ManageHook, Scratchpad};
+use crate::ewmh::{WindowPolicy, WindowType};
 use crate::statusbar::Statusbar;
 use crate::layout::*;
 
@@ -16,6 +17,7 @@ use std::path::{Path, PathBuf};
 use std::fs::{self, File};
 use std::process::{Command, Child, Stdio};
 use std::collections::HashMap;
+use std::time::Duration;
 
 use libloading::os::unix::{Library, Symbol};
 use anyhow::{bail, Context, Result};
@@ -36,6 +38,7 @@ pub extern "C" fn configure_workspaces() -> Vec<WorkspaceConfig> {
                 tag: idx.to_string(),
                 screen: 0,
                 layout: Strut::new(Choose::new(vec![Tall::new(1, 0.5, 0.05), Rotate::new(Tall::new(1, 0.5, 0.05)), Full::new(false)])),
+                project: None,
             }
         })
         .collect()
@@ -73,6 +76,7 @@ impl Default for WorkspaceConfigList {
                     tag: idx.to_string(),
                     screen: 0,
                     layout: Strut::new(Tall::new(1, 0.5, 0.05)),
+                    project: None,
                 }
             })
             .collect::<Vec<WorkspaceConfig>>()
@@ -93,12 +97,16 @@ pub struct WorkspaceInfo {
     pub current: bool,
     pub visible: bool,
     pub urgent: bool,
+    pub windows: Vec<String>,
+    pub minimized: Vec<String>,
+    pub hung: Vec<String>,
 }
 
 pub struct PagerInfo {
     pub workspaces: Vec<WorkspaceInfo>,
-    pub layout_names: Vec<String>,
+    pub layout_names: LayoutNames,
     pub window_title: String,
+    pub window_hung: bool,
 }
 
 pub struct Config {
@@ -109,9 +117,19 @@ pub struct Config {
     pub border_focus_color: u32,
     pub border_urgent_color: u32,
     pub greedy_view: bool,
+    pub auto_back_and_forth: bool,
+    pub respect_size_hints: bool,
+    pub float_on_moveresize: bool,
+    pub ping_timeout: Duration,
+    pub swallow_classes: Vec<String>,
     pub terminal: String,
+    pub default_layout: Box<dyn Fn() -> Box<dyn Layout>>,
+    pub menu: String,
+    pub menu_args: Vec<String>,
     pub keybindings: HashMap<Keybinding, Cmd>,
     pub manage_hooks: Vec<ManageHook>,
+    pub window_policies: HashMap<WindowType, WindowPolicy>,
+    pub scratchpads: Vec<Scratchpad>,
     pub statusbar: Option<Statusbar>,
 }
 
@@ -125,7 +143,15 @@ impl Default for Config {
             border_focus_color: 0x002a_82e6,
             border_urgent_color: 0x00ff_0000,
             greedy_view: false,
+            auto_back_and_forth: false,
+            respect_size_hints: false,
+            float_on_moveresize: false,
+            ping_timeout: Duration::from_secs(5),
+            swallow_classes: Vec::new(),
             terminal: "xterm".to_string(),
+            default_layout: Box::new(|| Strut::new(Tall::new(1, 0.5, 0.05))),
+            menu: "dmenu".to_string(),
+            menu_args: vec!["-i".to_string(), "-l".to_string(), "20".to_string()],
             keybindings: vec![(
                             Keybinding {
                                 mods: 0,
@@ -138,12 +164,30 @@ impl Default for Config {
                                 key: "d".to_string(),
                             },
                             Cmd::Exec("dmenu_run".to_string(), vec![])
+                        ),(
+                            Keybinding {
+                                mods: MOD_SHIFT,
+                                key: "d".to_string(),
+                            },
+                            Cmd::ToggleShowDesktop
                         ),(
                             Keybinding {
                                 mods: MOD_SHIFT,
                                 key: "q".to_string(),
                             },
                             Cmd::KillClient
+                        ),(
+                            Keybinding {
+                                mods: 0,
+                                key: "n".to_string(),
+                            },
+                            Cmd::Minimize
+                        ),(
+                            Keybinding {
+                                mods: MOD_SHIFT,
+                                key: "n".to_string(),
+                            },
+                            Cmd::RestoreLastMinimized
                         ),(
                             Keybinding {
                                 mods: 0,
@@ -279,6 +323,24 @@ impl Default for Config {
                         )]
                         .drain(0..).collect(),
             manage_hooks: Vec::new(),
+            window_policies: vec![
+                (WindowType::Desktop, WindowPolicy::Desktop),
+                (WindowType::Toolbar, WindowPolicy::Float),
+                (WindowType::Menu, WindowPolicy::Float),
+                (WindowType::Utility, WindowPolicy::Float),
+                (WindowType::Splash, WindowPolicy::Overlay),
+                (WindowType::Dialog, WindowPolicy::Float),
+                (WindowType::DropdownMenu, WindowPolicy::Overlay),
+                (WindowType::PopupMenu, WindowPolicy::Overlay),
+                (WindowType::Tooltip, WindowPolicy::Overlay),
+                (WindowType::Notification, WindowPolicy::Overlay),
+                (WindowType::Combo, WindowPolicy::Overlay),
+                (WindowType::Dnd, WindowPolicy::Overlay),
+                (WindowType::Normal, WindowPolicy::Tile),
+            ]
+            .into_iter()
+            .collect(),
+            scratchpads: Vec::new(),
             statusbar: None,
         };
 
@@ -296,6 +358,29 @@ impl Default for Config {
                 },
                 Cmd::MoveToWorkspace(i)
             );
+
+            config.keybindings.insert(Keybinding {
+                    mods: MOD_CONTROL | MOD_SHIFT,
+                    key: i.to_string()
+                },
+                Cmd::CopyToWorkspace(i)
+            );
+        }
+
+        for &(key, next) in &[("Right", true), ("Left", false)] {
+            config.keybindings.insert(Keybinding {
+                    mods: 0,
+                    key: key.to_string()
+                },
+                if next { Cmd::SwitchNextWorkspace(CycleMode::All) } else { Cmd::SwitchPrevWorkspace(CycleMode::All) }
+            );
+
+            config.keybindings.insert(Keybinding {
+                    mods: MOD_SHIFT,
+                    key: key.to_string()
+                },
+                if next { Cmd::MoveToNextWorkspace(CycleMode::All) } else { Cmd::MoveToPrevWorkspace(CycleMode::All) }
+            );
         }
 
         for &(i, key) in &[(1, "w"), (2, "e"), (3, "r")] {
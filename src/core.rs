@@ -3,7 +3,8 @@ extern crate log;
 
 pub mod core {
     pub mod commands {
-        pub use crate::commands::{Cmd, CmdManage, ManageHook};
+        pub use crate::commands::{Cmd, CmdManage, ManageHook, Scratchpad, ScratchpadRule};
+        pub use crate::workspace::CycleMode;
     }
 
     pub mod keycode {
@@ -20,8 +21,9 @@ pub mod core {
     }
 
     pub use crate::config::{Config, PagerInfo};
+    pub use crate::ewmh::{WindowPolicy, WindowType};
     pub use crate::statusbar::Statusbar;
-    pub use crate::workspace::WorkspaceConfig;
+    pub use crate::workspace::{Project, WorkspaceConfig};
 }
 
 mod xlib_window_system;
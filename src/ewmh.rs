@@ -1,7 +1,7 @@
 use crate::config::Config;
 use crate::state::WmState;
-use crate::workspace::Workspace;
-use crate::xlib_window_system::XlibWindowSystem;
+use crate::workspace::{Drag, Workspace};
+use crate::xlib_window_system::{XlibWindowSystem, WM_STATE_ICONIC};
 use std::ffi::CString;
 use x11::xlib::*;
 
@@ -9,6 +9,170 @@ pub const NET_WM_STATE_REMOVE: u64 = 0;
 pub const NET_WM_STATE_ADD: u64 = 1;
 pub const NET_WM_STATE_TOGGLE: u64 = 2;
 
+// _NET_WM_DESKTOP value for windows shown on all desktops
+pub const NET_WM_DESKTOP_ALL: u64 = 0xFFFF_FFFF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WindowType {
+    Desktop,
+    Toolbar,
+    Menu,
+    Utility,
+    Splash,
+    Dialog,
+    DropdownMenu,
+    PopupMenu,
+    Tooltip,
+    Notification,
+    Combo,
+    Dnd,
+    Normal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowPolicy {
+    Tile,
+    // floats centred over the transient parent or the screen
+    Float,
+    // stays unmanaged on top of all other windows and never takes the focus
+    Overlay,
+    // stays unmanaged below all other windows and covers all screens
+    Desktop,
+}
+
+fn parse_window_type(name: &str) -> Option<WindowType> {
+    match name {
+        "_NET_WM_WINDOW_TYPE_DESKTOP" => Some(WindowType::Desktop),
+        "_NET_WM_WINDOW_TYPE_TOOLBAR" => Some(WindowType::Toolbar),
+        "_NET_WM_WINDOW_TYPE_MENU" => Some(WindowType::Menu),
+        "_NET_WM_WINDOW_TYPE_UTILITY" => Some(WindowType::Utility),
+        "_NET_WM_WINDOW_TYPE_SPLASH" => Some(WindowType::Splash),
+        "_NET_WM_WINDOW_TYPE_DIALOG" => Some(WindowType::Dialog),
+        "_NET_WM_WINDOW_TYPE_DROPDOWN_MENU" => Some(WindowType::DropdownMenu),
+        "_NET_WM_WINDOW_TYPE_POPUP_MENU" => Some(WindowType::PopupMenu),
+        "_NET_WM_WINDOW_TYPE_TOOLTIP" => Some(WindowType::Tooltip),
+        "_NET_WM_WINDOW_TYPE_NOTIFICATION" => Some(WindowType::Notification),
+        "_NET_WM_WINDOW_TYPE_COMBO" => Some(WindowType::Combo),
+        "_NET_WM_WINDOW_TYPE_DND" => Some(WindowType::Dnd),
+        "_NET_WM_WINDOW_TYPE_NORMAL" => Some(WindowType::Normal),
+        _ => None,
+    }
+}
+
+// window types in order of preference
+pub fn get_window_types(xws: &XlibWindowSystem, window: Window) -> Vec<WindowType> {
+    let types: Vec<WindowType> = xws
+        .get_property(window, "_NET_WM_WINDOW_TYPE")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|x| parse_window_type(&xws.get_atom_name(x)))
+        .collect();
+
+    resolve_window_types(types, xws.transient_for(window).is_some())
+}
+
+// windows without a known type are normal windows. Transient normal windows
+// are treated as dialogs, many toolkits set the normal type on their dialogs
+fn resolve_window_types(types: Vec<WindowType>, is_transient: bool) -> Vec<WindowType> {
+    let types = if types.is_empty() { vec![WindowType::Normal] } else { types };
+
+    types
+        .into_iter()
+        .map(|x| if is_transient && x == WindowType::Normal { WindowType::Dialog } else { x })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum ClientRequest {
+    ActivateWindow,
+    CloseWindow,
+    RequestFrameExtents,
+    SwitchDesktop(usize),
+    SetNumberOfDesktops(usize),
+    MoveDesktop(usize, usize),
+    MoveToDesktop(usize),
+    MoveToAllDesktops,
+    ChangeState(u64, Vec<Atom>),
+    MoveResizeWindow {
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    StartDrag(Drag),
+    Iconify,
+    // reply to a WM_PROTOCOLS message sent by the wm
+    Protocol(Atom, Window),
+    ShowDesktop(bool),
+}
+
+// maps the _NET_WM_MOVERESIZE direction to a pointer drag. Keyboard driven
+// moves and cancel requests have no counterpart
+fn parse_drag_direction(direction: u64) -> Option<Drag> {
+    let resize = |left, top, right, bottom| Some(Drag::Resize { left, top, right, bottom });
+
+    match direction {
+        0 => resize(true, true, false, false),
+        1 => resize(false, true, false, false),
+        2 => resize(false, true, true, false),
+        3 => resize(false, false, true, false),
+        4 => resize(false, false, true, true),
+        5 => resize(false, false, false, true),
+        6 => resize(true, false, false, true),
+        7 => resize(true, false, false, false),
+        8 => Some(Drag::Move),
+        _ => None,
+    }
+}
+
+fn parse_client_message(msg_type: &str, msg_data: &[u64]) -> Option<ClientRequest> {
+    let arg = |idx: usize| msg_data.get(idx).copied();
+
+    match msg_type {
+        "_NET_ACTIVE_WINDOW" => Some(ClientRequest::ActivateWindow),
+        "_NET_CLOSE_WINDOW" => Some(ClientRequest::CloseWindow),
+        "_NET_REQUEST_FRAME_EXTENTS" => Some(ClientRequest::RequestFrameExtents),
+        "_NET_SHOWING_DESKTOP" => Some(ClientRequest::ShowDesktop(arg(0)? != 0)),
+        "_NET_CURRENT_DESKTOP" => Some(ClientRequest::SwitchDesktop(arg(0)? as usize)),
+        "_NET_NUMBER_OF_DESKTOPS" => Some(ClientRequest::SetNumberOfDesktops(arg(0)? as usize)),
+        "_XR3WM_MOVE_DESKTOP" => Some(ClientRequest::MoveDesktop(arg(0)? as usize, arg(1)? as usize)),
+        "_NET_WM_DESKTOP" => {
+            // Xlib sign extends the 32 bit value on 64 bit systems
+            match arg(0)? & 0xFFFF_FFFF {
+                NET_WM_DESKTOP_ALL => Some(ClientRequest::MoveToAllDesktops),
+                idx => Some(ClientRequest::MoveToDesktop(idx as usize)),
+            }
+        }
+        "_NET_MOVERESIZE_WINDOW" => {
+            // bits 8 to 11 of the flags mark which of x, y, width and height are set
+            let flags = arg(0)?;
+            let value = |idx: usize| Some(arg(idx + 1)?).filter(|_| flags & (1 << (8 + idx)) != 0);
+
+            Some(ClientRequest::MoveResizeWindow {
+                x: value(0).map(|x| x as i32),
+                y: value(1).map(|y| y as i32),
+                width: value(2).map(|w| w as u32),
+                height: value(3).map(|h| h as u32),
+            })
+        }
+        "_NET_WM_MOVERESIZE" => parse_drag_direction(arg(2)?).map(ClientRequest::StartDrag),
+        // ICCCM 4.1.4, sent by XIconifyWindow
+        "WM_CHANGE_STATE" if arg(0)? == WM_STATE_ICONIC => Some(ClientRequest::Iconify),
+        "WM_PROTOCOLS" => Some(ClientRequest::Protocol(arg(0)?, arg(2)?)),
+        "_NET_WM_STATE" => {
+            let atoms = msg_data
+                .get(1..3)?
+                .iter()
+                .filter(|&x| *x != 0)
+                .cloned()
+                .collect();
+
+            Some(ClientRequest::ChangeState(arg(0)?, atoms))
+        }
+        _ => None,
+    }
+}
+
 pub fn init_ewmh(xws: &mut XlibWindowSystem) {
     debug!("initializing ewmh");
     let root = xws.get_root_window();
@@ -16,11 +180,20 @@ pub fn init_ewmh(xws: &mut XlibWindowSystem) {
     let atoms = &[
         "_NET_SUPPORTED",
         "_NET_ACTIVE_WINDOW",
+        "_NET_CLOSE_WINDOW",
+        "_NET_MOVERESIZE_WINDOW",
+        "_NET_WM_MOVERESIZE",
+        "_NET_WM_PING",
+        "_NET_SHOWING_DESKTOP",
         "_NET_CLIENT_LIST",
+        "_NET_CLIENT_LIST_STACKING",
         "_NET_CURRENT_DESKTOP",
+        "_NET_DESKTOP_GEOMETRY",
         "_NET_DESKTOP_NAMES",
         "_NET_DESKTOP_VIEWPORT",
+        "_NET_FRAME_EXTENTS",
         "_NET_NUMBER_OF_DESKTOPS",
+        "_NET_REQUEST_FRAME_EXTENTS",
         "_NET_WM_DESKTOP",
         "_NET_SUPPORTING_WM_CHECK",
         "_NET_WM_NAME",
@@ -28,9 +201,30 @@ pub fn init_ewmh(xws: &mut XlibWindowSystem) {
         "_NET_WM_STATE_FULLSCREEN",
         "_NET_WM_STATE_DEMANDS_ATTENTION",
         "_NET_WM_STATE_STICKY",
+        "_NET_WM_STATE_MAXIMIZED_VERT",
+        "_NET_WM_STATE_MAXIMIZED_HORZ",
+        "_NET_WM_STATE_HIDDEN",
+        "_NET_WM_STATE_ABOVE",
+        "_NET_WM_STATE_BELOW",
+        "_NET_WM_STATE_SKIP_TASKBAR",
+        "_NET_WM_STATE_SKIP_PAGER",
         "_NET_WM_STRUT_PARTIAL",
         "_NET_WM_WINDOW_TYPE",
         "_NET_WM_WINDOW_TYPE_DOCK",
+        "_NET_WM_WINDOW_TYPE_DESKTOP",
+        "_NET_WM_WINDOW_TYPE_TOOLBAR",
+        "_NET_WM_WINDOW_TYPE_MENU",
+        "_NET_WM_WINDOW_TYPE_UTILITY",
+        "_NET_WM_WINDOW_TYPE_SPLASH",
+        "_NET_WM_WINDOW_TYPE_DIALOG",
+        "_NET_WM_WINDOW_TYPE_DROPDOWN_MENU",
+        "_NET_WM_WINDOW_TYPE_POPUP_MENU",
+        "_NET_WM_WINDOW_TYPE_TOOLTIP",
+        "_NET_WM_WINDOW_TYPE_NOTIFICATION",
+        "_NET_WM_WINDOW_TYPE_COMBO",
+        "_NET_WM_WINDOW_TYPE_DND",
+        "_NET_WM_WINDOW_TYPE_NORMAL",
+        "_NET_WORKAREA",
     ];
 
     xws.cache_atoms(atoms);
@@ -73,20 +267,80 @@ pub fn process_client_message(
     msg_type: Atom,
     msg_data: &[u64],
 ) {
-    match xws.get_atom_name(msg_type).as_str() {
-        "_NET_ACTIVE_WINDOW" => {
+    let Some(request) = parse_client_message(&xws.get_atom_name(msg_type), msg_data) else {
+        return;
+    };
+
+    match request {
+        ClientRequest::ActivateWindow => {
             state.focus_window(xws, config, window, true);
         }
-        "_NET_CURRENT_DESKTOP" => {
-            state.switch_to_ws(xws, config, msg_data[0] as usize, true);
+        ClientRequest::CloseWindow => {
+            if state.contains(window) {
+                xws.kill_window(window);
+            }
         }
-        "_NET_WM_STATE" => {
-            let mode = msg_data[0];
-            let wm_states: Vec<u64> = msg_data[1..3]
-                .iter()
-                .filter(|&x| *x != 0)
-                .cloned()
-                .collect();
+        ClientRequest::Iconify => {
+            if state.contains(window) {
+                state.minimize_window(xws, config, window);
+            }
+        }
+        ClientRequest::ShowDesktop(showing) => {
+            state.show_desktop(xws, config, showing);
+        }
+        ClientRequest::Protocol(protocol, client) => {
+            if protocol == xws.get_atom("_NET_WM_PING") {
+                state.pong(client);
+            }
+        }
+        ClientRequest::RequestFrameExtents => {
+            // the window is not mapped yet and gets the default border
+            set_frame_extents(xws, window, config.border_width);
+        }
+        ClientRequest::SwitchDesktop(index) => {
+            state.switch_to_ws(xws, config, index, true);
+        }
+        ClientRequest::SetNumberOfDesktops(num_desktops) => {
+            while state.ws_count() < num_desktops {
+                let tag = (state.ws_count() + 1).to_string();
+                state.add_ws(xws, &tag, (config.default_layout)());
+            }
+
+            // stop at the first workspace that can not be removed
+            while state.ws_count() > num_desktops.max(1) {
+                if !state.remove_ws(xws, config, state.ws_count() - 1) {
+                    set_number_of_desktops(xws, state.ws_count());
+                    break;
+                }
+            }
+        }
+        ClientRequest::MoveDesktop(from, to) => {
+            state.move_ws(xws, from, to);
+        }
+        ClientRequest::MoveToDesktop(index) => {
+            if state.contains(window) {
+                state.send_window_to_ws(xws, config, window, index);
+            }
+        }
+        ClientRequest::MoveToAllDesktops => {
+            if state.contains(window) {
+                state.copy_window_to_all(xws, config, window);
+            }
+        }
+        ClientRequest::MoveResizeWindow { x, y, width, height } => {
+            if state.contains(window) {
+                state.move_resize_window(xws, config, window, (x, y), (width, height));
+            }
+        }
+        ClientRequest::StartDrag(drag) => {
+            if state.contains(window) {
+                state.drag_window(xws, config, window, drag);
+            }
+        }
+        ClientRequest::ChangeState(mode, wm_states) => {
+            // hidden follows the minimized state of the window and can not be set by clients
+            let hidden = xws.get_atom("_NET_WM_STATE_HIDDEN");
+            let wm_states: Vec<Atom> = wm_states.into_iter().filter(|&x| x != hidden).collect();
 
             let mut redraw = false;
             let ret = set_wm_state(xws, window, &wm_states, mode);
@@ -95,16 +349,28 @@ pub fn process_client_message(
                 let fullscreen = xws.get_atom("_NET_WM_STATE_FULLSCREEN");
                 let attention = xws.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
                 let sticky = xws.get_atom("_NET_WM_STATE_STICKY");
+                let above = xws.get_atom("_NET_WM_STATE_ABOVE");
+                let below = xws.get_atom("_NET_WM_STATE_BELOW");
+                let geometry_states = [
+                    fullscreen,
+                    above,
+                    below,
+                    xws.get_atom("_NET_WM_STATE_MAXIMIZED_VERT"),
+                    xws.get_atom("_NET_WM_STATE_MAXIMIZED_HORZ"),
+                ];
 
                 for s in add_states {
                     if s == attention {
                         state.set_urgency(true, window);
                         redraw = true;
                     } else if s == sticky {
-                        state.remove_window(xws, config, window);
-                        state.add_unmanaged(window);
+                        state.copy_window_to_all(xws, config, window);
+                    } else if s == above || s == below {
+                        // a window is either kept above or below the others
+                        let other = if s == above { below } else { above };
+                        set_wm_state(xws, window, &[other], NET_WM_STATE_REMOVE);
                         redraw = true;
-                    } else if s == fullscreen {
+                    } else if geometry_states.contains(&s) {
                         redraw = true;
                     }
                 }
@@ -114,10 +380,11 @@ pub fn process_client_message(
                     redraw = true;
                 }
 
-                if rem_states
-                    .iter()
-                    .any(|&x| x == xws.get_atom("_NET_WM_STATE_FULLSCREEN"))
-                {
+                if rem_states.contains(&sticky) {
+                    state.remove_other_copies(xws, config, window);
+                }
+
+                if rem_states.iter().any(|x| geometry_states.contains(x)) {
                     redraw = true;
                 }
             }
@@ -126,7 +393,6 @@ pub fn process_client_message(
                 state.redraw(xws, config);
             }
         }
-        _ => {}
     }
 }
 
@@ -142,6 +408,17 @@ pub fn set_active_window(xws: &XlibWindowSystem, window: Window) {
     );
 }
 
+pub fn set_showing_desktop(xws: &XlibWindowSystem, showing: bool) {
+    let root = xws.get_root_window();
+    xws.change_property(
+        root,
+        "_NET_SHOWING_DESKTOP",
+        XA_CARDINAL,
+        PropModeReplace,
+        &[showing as u64],
+    );
+}
+
 pub fn get_active_window(xws: &XlibWindowSystem) -> Option<Window> {
     let root = xws.get_root_window();
     xws.get_property(root, "_NET_ACTIVE_WINDOW").map(|x| x[0])
@@ -215,16 +492,58 @@ pub fn set_desktop_viewport(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
     );
 }
 
-pub fn set_client_list(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
+pub fn set_desktop_geometry(xws: &XlibWindowSystem) {
     let root = xws.get_root_window();
+    let rect = xws.get_display_rect();
 
-    let clients: Vec<Window> = workspaces
+    xws.change_property(
+        root,
+        "_NET_DESKTOP_GEOMETRY",
+        XA_CARDINAL,
+        PropModeReplace,
+        &[rect.width as u64, rect.height as u64],
+    );
+}
+
+pub fn set_workarea(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
+    let root = xws.get_root_window();
+    let screens = xws.get_screen_infos();
+
+    let workareas: Vec<u64> = workspaces
         .iter()
-        .map(|ws| ws.all())
-        .collect::<Vec<Vec<Window>>>()
+        .map(|ws| {
+            let screen = screens
+                .get(ws.get_screen())
+                .copied()
+                .unwrap_or_else(|| xws.get_display_rect());
+            let area = xws.get_work_area(screen);
+
+            vec![area.x as u64, area.y as u64, area.width as u64, area.height as u64]
+        })
+        .collect::<Vec<Vec<u64>>>()
         .as_slice()
         .concat();
 
+    xws.change_property(
+        root,
+        "_NET_WORKAREA",
+        XA_CARDINAL,
+        PropModeReplace,
+        &workareas,
+    );
+}
+
+pub fn set_client_list(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
+    let root = xws.get_root_window();
+
+    // copies of a window on several workspaces are listed once
+    let mut clients: Vec<Window> = Vec::new();
+    for window in workspaces.iter().flat_map(|ws| ws.all()) {
+        if !clients.contains(&window) {
+            clients.push(window);
+        }
+    }
+
     xws.change_property(
         root,
         "_NET_CLIENT_LIST",
@@ -232,6 +551,36 @@ pub fn set_client_list(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
         PropModeReplace,
         &clients,
     );
+
+    set_client_list_stacking(xws, workspaces);
+}
+
+// the managed windows from bottom to top. Hidden workspaces come first and the
+// current one last, copies of a window are listed at their topmost position
+pub fn set_client_list_stacking(xws: &XlibWindowSystem, workspaces: &[Workspace]) {
+    let root = xws.get_root_window();
+
+    let mut ordered: Vec<&Workspace> = workspaces.iter().collect();
+    ordered.sort_by_key(|ws| (ws.is_visible(), ws.focus));
+
+    let mut stacking: Vec<Window> = Vec::new();
+    for window in ordered.iter().flat_map(|ws| ws.stacking_order(xws)) {
+        stacking.retain(|&w| w != window);
+        stacking.push(window);
+    }
+
+    xws.change_property(
+        root,
+        "_NET_CLIENT_LIST_STACKING",
+        XA_WINDOW,
+        PropModeReplace,
+        &stacking,
+    );
+}
+
+pub fn get_wm_pid(xws: &XlibWindowSystem, window: Window) -> Option<u64> {
+    xws.get_property(window, "_NET_WM_PID")
+        .and_then(|x| x.first().copied())
 }
 
 pub fn set_wm_desktop(xws: &XlibWindowSystem, window: Window, idx: usize) {
@@ -240,10 +589,25 @@ pub fn set_wm_desktop(xws: &XlibWindowSystem, window: Window, idx: usize) {
         "_NET_WM_DESKTOP",
         XA_CARDINAL,
         PropModeReplace,
-        &[idx as u32],
+        &[idx as u64],
     );
 }
 
+// only written on changes to avoid flooding clients with property notifications
+pub fn set_frame_extents(xws: &XlibWindowSystem, window: Window, border_width: u32) {
+    let extents = [border_width as u64; 4];
+
+    if xws.get_property(window, "_NET_FRAME_EXTENTS").as_deref() != Some(&extents[..]) {
+        xws.change_property(
+            window,
+            "_NET_FRAME_EXTENTS",
+            XA_CARDINAL,
+            PropModeReplace,
+            &extents,
+        );
+    }
+}
+
 pub fn set_wm_state(
     xws: &XlibWindowSystem,
     window: Window,
@@ -292,20 +656,142 @@ pub fn set_wm_state(
     }
 }
 
+pub fn has_wm_state(xws: &XlibWindowSystem, window: Window, wm_state: &str) -> bool {
+    xws.get_wm_state(window).contains(&xws.get_atom(wm_state))
+}
+
+// windows with either skip state are left out of the bar and the window switcher alike
+pub fn is_skipped(xws: &XlibWindowSystem, window: Window) -> bool {
+    has_wm_state(xws, window, "_NET_WM_STATE_SKIP_TASKBAR")
+        || has_wm_state(xws, window, "_NET_WM_STATE_SKIP_PAGER")
+}
+
 pub fn is_window_sticky(xws: &XlibWindowSystem, window: Window) -> bool {
-     xws.get_property(window, "_NET_WM_STATE")
-        .map(|prop| {
-            prop.iter()
-                .any(|&x| x == xws.get_atom("_NET_WM_STATE_STICKY"))
-        })
-        .unwrap_or(false)
+    has_wm_state(xws, window, "_NET_WM_STATE_STICKY")
 }
 
 pub fn is_window_fullscreen(xws: &XlibWindowSystem, window: Window) -> bool {
-    xws.get_property(window, "_NET_WM_STATE")
-        .map(|prop| {
-            prop.iter()
-                .any(|&x| x == xws.get_atom("_NET_WM_STATE_FULLSCREEN"))
-        })
-        .unwrap_or(false)
+    has_wm_state(xws, window, "_NET_WM_STATE_FULLSCREEN")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xlib_window_system::WM_STATE_NORMAL;
+
+    #[test]
+    fn parse_close_window() {
+        assert_eq!(
+            parse_client_message("_NET_CLOSE_WINDOW", &[0, 2, 0, 0, 0]),
+            Some(ClientRequest::CloseWindow)
+        );
+    }
+
+    #[test]
+    fn parse_request_frame_extents() {
+        assert_eq!(
+            parse_client_message("_NET_REQUEST_FRAME_EXTENTS", &[0, 0, 0, 0, 0]),
+            Some(ClientRequest::RequestFrameExtents)
+        );
+    }
+
+    #[test]
+    fn parse_wm_desktop() {
+        assert_eq!(
+            parse_client_message("_NET_WM_DESKTOP", &[3, 2, 0, 0, 0]),
+            Some(ClientRequest::MoveToDesktop(3))
+        );
+        assert_eq!(
+            parse_client_message("_NET_WM_DESKTOP", &[NET_WM_DESKTOP_ALL, 2, 0, 0, 0]),
+            Some(ClientRequest::MoveToAllDesktops)
+        );
+        assert_eq!(
+            parse_client_message("_NET_WM_DESKTOP", &[u64::MAX, 2, 0, 0, 0]),
+            Some(ClientRequest::MoveToAllDesktops)
+        );
+        assert_eq!(parse_client_message("_NET_WM_DESKTOP", &[]), None);
+    }
+
+    #[test]
+    fn parse_change_state() {
+        assert_eq!(
+            parse_client_message("WM_CHANGE_STATE", &[WM_STATE_ICONIC, 0, 0, 0, 0]),
+            Some(ClientRequest::Iconify)
+        );
+        assert_eq!(parse_client_message("WM_CHANGE_STATE", &[WM_STATE_NORMAL, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn parse_showing_desktop() {
+        assert_eq!(
+            parse_client_message("_NET_SHOWING_DESKTOP", &[1, 0, 0, 0, 0]),
+            Some(ClientRequest::ShowDesktop(true))
+        );
+        assert_eq!(
+            parse_client_message("_NET_SHOWING_DESKTOP", &[0, 0, 0, 0, 0]),
+            Some(ClientRequest::ShowDesktop(false))
+        );
+    }
+
+    #[test]
+    fn parse_pong() {
+        assert_eq!(
+            parse_client_message("WM_PROTOCOLS", &[42, 1000, 0x1e0_0001, 0, 0]),
+            Some(ClientRequest::Protocol(42, 0x1e0_0001))
+        );
+        assert_eq!(parse_client_message("WM_PROTOCOLS", &[42, 1000]), None);
+    }
+
+    #[test]
+    fn parse_wm_state() {
+        assert_eq!(
+            parse_client_message("_NET_WM_STATE", &[NET_WM_STATE_ADD, 42, 0, 1, 0]),
+            Some(ClientRequest::ChangeState(NET_WM_STATE_ADD, vec![42]))
+        );
+    }
+
+    #[test]
+    fn parse_moveresize_window() {
+        assert_eq!(
+            parse_client_message("_NET_MOVERESIZE_WINDOW", &[(1 << 8) | (1 << 11), 10, 20, 30, 40]),
+            Some(ClientRequest::MoveResizeWindow { x: Some(10), y: None, width: None, height: Some(40) })
+        );
+    }
+
+    #[test]
+    fn parse_wm_moveresize() {
+        assert_eq!(
+            parse_client_message("_NET_WM_MOVERESIZE", &[100, 100, 8, 1, 1]),
+            Some(ClientRequest::StartDrag(Drag::Move))
+        );
+        assert_eq!(
+            parse_client_message("_NET_WM_MOVERESIZE", &[100, 100, 4, 1, 1]),
+            Some(ClientRequest::StartDrag(Drag::Resize { left: false, top: false, right: true, bottom: true }))
+        );
+        assert_eq!(parse_client_message("_NET_WM_MOVERESIZE", &[100, 100, 11, 1, 1]), None);
+    }
+
+    #[test]
+    fn parse_window_types() {
+        assert_eq!(parse_window_type("_NET_WM_WINDOW_TYPE_DIALOG"), Some(WindowType::Dialog));
+        assert_eq!(parse_window_type("_NET_WM_WINDOW_TYPE_NOTIFICATION"), Some(WindowType::Notification));
+        assert_eq!(parse_window_type("_NET_WM_WINDOW_TYPE_DOCK"), None);
+        assert_eq!(parse_window_type("_KDE_NET_WM_WINDOW_TYPE_OVERRIDE"), None);
+    }
+
+    #[test]
+    fn transient_window_types() {
+        assert_eq!(resolve_window_types(vec![], false), vec![WindowType::Normal]);
+        assert_eq!(resolve_window_types(vec![], true), vec![WindowType::Dialog]);
+        assert_eq!(resolve_window_types(vec![WindowType::Normal], true), vec![WindowType::Dialog]);
+        assert_eq!(
+            resolve_window_types(vec![WindowType::Utility, WindowType::Normal], true),
+            vec![WindowType::Utility, WindowType::Dialog]
+        );
+    }
+
+    #[test]
+    fn parse_unknown() {
+        assert_eq!(parse_client_message("_NET_UNKNOWN", &[0, 0, 0, 0, 0]), None);
+    }
 }
@@ -6,6 +6,7 @@ use crate::xlib_window_system::XlibWindowSystem;
 use crate::ewmh;
 use std::cmp::min;
 use std::fmt;
+use std::ops::Range;
 use x11::xlib::Window;
 
 #[cfg(feature = "reload")]
@@ -64,6 +65,15 @@ impl fmt::Debug for LayoutMsg {
     }
 }
 
+// layout names along the focus path: the workspace layout, the containers in between
+// and the innermost container holding the focused window (None if not nested)
+#[derive(Clone, Default, Debug)]
+pub struct LayoutNames {
+    pub outer: String,
+    pub selected: Option<String>,
+    pub nested: Vec<String>,
+}
+
 #[cfg_attr(feature = "reload", typetag::serde(tag = "type"))]
 pub trait Layout {
     fn name(&self) -> String;
@@ -76,6 +86,11 @@ pub trait Layout {
     fn simple_apply(&self, _: Rect, _: &[Node]) -> Vec<Rect> {
         Vec::new()
     }
+
+    // nodes that get a rect from apply. Everything else is hidden
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        0..stack.len()
+    }
 }
 
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
@@ -139,6 +154,10 @@ impl Layout for Choose {
     fn apply(&self, area: Rect, xws: &XlibWindowSystem, stack: &Stack) -> Vec<Rect> {
         self.layouts[self.current].apply(area, xws, stack)
     }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layouts[self.current].visible_nodes(stack)
+    }
 }
 
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
@@ -166,21 +185,15 @@ impl Layout for Tall {
 
     fn send_msg(&mut self, _: &XlibWindowSystem, _: &[Node], msg: LayoutMsg) {
         match msg {
-            LayoutMsg::Increase => {
-                if self.ratio + self.ratio_increment < 1.0 {
-                    self.ratio += self.ratio_increment;
-                }
+            LayoutMsg::Increase if self.ratio + self.ratio_increment < 1.0 => {
+                self.ratio += self.ratio_increment;
             }
-            LayoutMsg::Decrease => {
-                if self.ratio - self.ratio_increment > self.ratio_increment {
-                    self.ratio -= self.ratio_increment;
-                }
+            LayoutMsg::Decrease if self.ratio - self.ratio_increment > self.ratio_increment => {
+                self.ratio -= self.ratio_increment;
             }
             LayoutMsg::IncreaseMaster => self.num_masters += 1,
-            LayoutMsg::DecreaseMaster => {
-                if self.num_masters > 1 {
-                    self.num_masters -= 1;
-                }
+            LayoutMsg::DecreaseMaster if self.num_masters > 1 => {
+                self.num_masters -= 1;
             }
             _ => {}
         }
@@ -243,20 +256,105 @@ impl Layout for Strut {
     }
 
     fn apply(&self, area: Rect, xws: &XlibWindowSystem, stack: &Stack) -> Vec<Rect> {
-        let mut new_area = Rect {
-            x: 0,
-            y: 0,
-            width: 0,
-            height: 0,
-        };
-        let strut = xws.compute_struts(area);
+        self.layout.apply(xws.get_work_area(area), xws, stack)
+    }
 
-        new_area.x = area.x + strut.0;
-        new_area.width = area.width - (strut.0 + strut.1);
-        new_area.y = area.y + strut.2;
-        new_area.height = area.height - (strut.2 + strut.3);
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layout.visible_nodes(stack)
+    }
+}
 
-        self.layout.apply(new_area, xws, stack)
+#[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
+pub struct Named {
+    name: String,
+    layout: Box<dyn Layout>,
+}
+
+impl Named {
+    pub fn new(name: &str, layout: Box<dyn Layout>) -> Box<dyn Layout> {
+        Box::new(Named {
+            name: name.to_string(),
+            layout,
+        })
+    }
+}
+
+#[cfg_attr(feature = "reload", typetag::serde)]
+impl Layout for Named {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn send_msg(&mut self, xws: &XlibWindowSystem, nodes: &[Node], msg: LayoutMsg) {
+        self.layout.send_msg(xws, nodes, msg);
+    }
+
+    fn apply(&self, area: Rect, xws: &XlibWindowSystem, stack: &Stack) -> Vec<Rect> {
+        self.layout.apply(area, xws, stack)
+    }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layout.visible_nodes(stack)
+    }
+}
+
+#[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
+pub enum LimitStyle {
+    First,
+    Focus,
+}
+
+#[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
+pub struct LimitWindows {
+    num_windows: usize,
+    style: LimitStyle,
+    layout: Box<dyn Layout>,
+}
+
+impl LimitWindows {
+    pub fn new(num_windows: usize, style: LimitStyle, layout: Box<dyn Layout>) -> Box<dyn Layout> {
+        Box::new(LimitWindows {
+            num_windows: num_windows.max(1),
+            style,
+            layout,
+        })
+    }
+}
+
+#[cfg_attr(feature = "reload", typetag::serde)]
+impl Layout for LimitWindows {
+    fn name(&self) -> String {
+        self.layout.name()
+    }
+
+    fn send_msg(&mut self, xws: &XlibWindowSystem, nodes: &[Node], msg: LayoutMsg) {
+        match msg {
+            LayoutMsg::Custom(x) if x.as_str() == "IncreaseLimit" => {
+                self.num_windows += 1;
+            }
+            LayoutMsg::Custom(x) if x.as_str() == "DecreaseLimit" && self.num_windows > 1 => {
+                self.num_windows -= 1;
+            }
+            x => self.layout.send_msg(xws, nodes, x),
+        }
+    }
+
+    fn apply(&self, area: Rect, xws: &XlibWindowSystem, stack: &Stack) -> Vec<Rect> {
+        self.layout.apply(area, xws, &stack.view(self.visible_nodes(stack)))
+    }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        let len = stack.len();
+        let focus = stack.focus.unwrap_or(0);
+
+        let start = match self.style {
+            // keep the first windows visible and only scroll if the focus leaves them
+            LimitStyle::First => (focus + 1).saturating_sub(self.num_windows),
+            LimitStyle::Focus => focus.saturating_sub((self.num_windows - 1) / 2),
+        };
+        let start = min(start, len.saturating_sub(self.num_windows));
+
+        start..min(start + self.num_windows, len)
     }
 }
 
@@ -395,6 +493,10 @@ impl Layout for Gap {
 
         rects
     }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layout.visible_nodes(stack)
+    }
 }
 
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
@@ -442,6 +544,10 @@ impl Layout for Mirror {
 
         rects
     }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layout.visible_nodes(stack)
+    }
 }
 
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
@@ -485,6 +591,10 @@ impl Layout for Rotate {
             })
             .collect()
     }
+
+    fn visible_nodes(&self, stack: &Stack) -> Range<usize> {
+        self.layout.visible_nodes(stack)
+    }
 }
 
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
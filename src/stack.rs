@@ -1,16 +1,17 @@
 #![allow(dead_code)]
 
-use crate::layout::{Layout, LayoutMsg, Rect};
+use crate::layout::{Layout, LayoutMsg, LayoutNames, Rect};
 use crate::workspace::MoveOp;
 use crate::xlib_window_system::XlibWindowSystem;
 use anyhow::{anyhow, Context, Result};
 use std::cmp;
+use std::ops::Range;
 use x11::xlib::Window;
 
 #[cfg(feature = "reload")]
 use serde::{Deserialize, Serialize};
 
-pub struct LayoutIter<'a> {
+struct LayoutIter<'a> {
     stack: Option<&'a Stack>,
 }
 
@@ -41,6 +42,15 @@ pub enum Node {
     Stack(Stack),
 }
 
+impl Node {
+    fn shallow_copy(&self) -> Node {
+        match self {
+            Node::Window(w) => Node::Window(*w),
+            Node::Stack(s) => Node::Stack(s.view(0..s.len())),
+        }
+    }
+}
+
 #[derive(Default)]
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
 pub struct Stack {
@@ -108,6 +118,20 @@ impl Stack {
         })
     }
 
+    // layout-less copy of a range of nodes for layouts that only arrange a subset of windows
+    pub fn view(&self, range: Range<usize>) -> Stack {
+        let range = cmp::min(range.start, self.len())..cmp::min(range.end, self.len());
+
+        Stack {
+            layout: None,
+            focus: self.focus
+                .and_then(|x| x.checked_sub(range.start))
+                .filter(|&x| x < range.len()),
+            nodes: self.nodes[range].iter().map(|x| x.shallow_copy()).collect(),
+            urgent: Vec::new(),
+        }
+    }
+
     pub fn all(&self) -> Vec<&Node> {
         self.nodes.iter().collect()
     }
@@ -177,8 +201,17 @@ impl Stack {
             .collect()
     }
 
-    pub fn layout_iter(&self) -> LayoutIter<'_> {
-        LayoutIter { stack: Some(self) }
+    pub fn layout_iter(&self) -> LayoutNames {
+        let mut names = LayoutIter { stack: Some(self) }.map(|x| x.name());
+        let outer = names.next().unwrap_or_default();
+        let mut nested: Vec<String> = names.collect();
+        let selected = nested.pop();
+
+        LayoutNames {
+            outer,
+            selected,
+            nested,
+        }
     }
 
     pub fn focused_window(&self) -> Option<Window> {
@@ -389,6 +422,47 @@ impl Stack {
         false
     }
 
+    pub fn insert_after(&mut self, target: Window, window: Window) -> bool {
+        let idx = self.nodes
+            .iter()
+            .position(|x| matches!(x, Node::Window(w) if *w == target));
+
+        if let Some(idx) = idx {
+            self.nodes.insert(idx + 1, Node::Window(window));
+            self.focus = self.focus.map(|x| if x > idx { x + 1 } else { x });
+            true
+        } else {
+            self.all_stacks_mut()
+                .into_iter()
+                .any(|s| s.insert_after(target, window))
+        }
+    }
+
+    // exchanges the places of the two windows, either of them may be in another stack
+    pub fn swap_windows(&mut self, window: Window, other: Window) {
+        for node in self.nodes.iter_mut() {
+            match node {
+                Node::Window(w) if *w == window => *w = other,
+                Node::Window(w) if *w == other => *w = window,
+                Node::Window(_) => (),
+                Node::Stack(s) => s.swap_windows(window, other),
+            }
+        }
+    }
+
+    pub fn replace_window(&mut self, window: Window, new_window: Window) -> bool {
+        self.remove_urgent(window);
+
+        self.nodes.iter_mut().any(|node| match node {
+            Node::Window(w) if *w == window => {
+                *w = new_window;
+                true
+            }
+            Node::Window(_) => false,
+            Node::Stack(s) => s.replace_window(window, new_window),
+        })
+    }
+
     pub fn remove_urgent(&mut self, window: Window) -> bool {
         let res = self
             .urgent
@@ -423,18 +497,42 @@ impl Stack {
         }
     }
 
+    fn visible_nodes(&self) -> Range<usize> {
+        let range = self.layout
+            .as_ref()
+            .map(|x| x.visible_nodes(self))
+            .unwrap_or(0..self.len());
+
+        cmp::min(range.start, self.len())..cmp::min(range.end, self.len())
+    }
+
+    pub fn hidden_windows(&self) -> Vec<Window> {
+        let range = self.visible_nodes();
+
+        self.nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, node)| match node {
+                Node::Window(w) if !range.contains(&idx) => vec![*w],
+                Node::Window(_) => Vec::new(),
+                Node::Stack(s) if !range.contains(&idx) => s.all_windows(),
+                Node::Stack(s) => s.hidden_windows(),
+            })
+            .collect()
+    }
+
+    // layouts may return fewer rects than there are visible nodes. Windows
+    // without a rect are left out and have to be hidden by the caller
     pub fn apply_layout(&self, screen: Rect, xws: &XlibWindowSystem) -> Vec<(Rect, Window)> {
         if let Some(layout) = self.layout.as_ref() {
             layout
                 .apply(screen, xws, self)
                 .iter()
-                .enumerate()
-                .filter_map(|(idx, rect)| match self.nodes.get(idx) {
-                    Some(Node::Window(w)) => Some(vec![(*rect, *w)]),
-                    Some(Node::Stack(s)) => Some(s.apply_layout(*rect, xws)),
-                    _ => None,
+                .zip(self.nodes[self.visible_nodes()].iter())
+                .flat_map(|(rect, node)| match node {
+                    Node::Window(w) => vec![(*rect, *w)],
+                    Node::Stack(s) => s.apply_layout(*rect, xws),
                 })
-                .flatten()
                 .collect()
         } else {
             Vec::new()
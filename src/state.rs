@@ -1,11 +1,16 @@
+use crate::commands::MenuAction;
 use crate::config::Config;
 use crate::stack::Stack;
-use crate::workspace::{Workspace, WorkspaceConfig};
+use crate::workspace::{CycleMode, Drag, Workspace, WorkspaceConfig};
 use crate::xlib_window_system::XlibWindowSystem;
-use crate::layout::Rect;
-use crate::ewmh;
-use std::cmp;
+use crate::layout::{Layout, Rect};
+use crate::ewmh::{self, WindowPolicy};
+use crate::utils;
+use std::{cmp, mem};
 use std::path::Path;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
 use x11::xlib::Window;
 use anyhow::{Result, Context};
 
@@ -16,7 +21,39 @@ use serde::{Serialize, Deserialize};
 pub struct WmState {
     workspaces: Vec<Workspace>,
     unmanaged: Vec<Window>,
+    // desktop windows kept below everything else
+    #[cfg_attr(feature = "reload", serde(default))]
+    desktops: Vec<Window>,
+    // (window, swallowed terminal) pairs
+    #[cfg_attr(feature = "reload", serde(default))]
+    swallowed: Vec<(Window, Window)>,
+    // scratchpad windows by name. Hidden scratchpads are not part of any workspace
+    #[cfg_attr(feature = "reload", serde(default))]
+    scratchpads: Vec<(String, Window)>,
+    // most recently focused windows first
+    #[cfg_attr(feature = "reload", serde(default))]
+    focus_history: Vec<Window>,
+    #[cfg_attr(feature = "reload", serde(skip))]
+    history_cursor: Option<usize>,
+    // (pid, workspace) of spawned processes whose first window is still pending
+    #[cfg_attr(feature = "reload", serde(default))]
+    spawns: Vec<(u64, usize)>,
+    // unanswered _NET_WM_PING requests and clients that missed the timeout
+    #[cfg_attr(feature = "reload", serde(skip))]
+    pings: Vec<(Window, Instant)>,
+    #[cfg_attr(feature = "reload", serde(skip))]
+    hung: Vec<Window>,
+    #[cfg_attr(feature = "reload", serde(skip))]
+    hung_changed: bool,
+    // the menu program running in the background and what to do with its selection
+    #[cfg_attr(feature = "reload", serde(skip))]
+    menu: Option<(UnixStream, MenuAction)>,
+    // persisted so a reload can reveal the concealed workspaces again
+    #[cfg_attr(feature = "reload", serde(default))]
+    showing_desktop: bool,
     cur: usize,
+    #[cfg_attr(feature = "reload", serde(default))]
+    prev: Option<usize>,
     screens: Vec<Rect>
 }
 
@@ -38,6 +75,14 @@ impl WmState {
                         })
                     });
 
+                    state.scratchpads.iter().for_each(|&(_, window)| {
+                        xws.request_window_events(window);
+                    });
+
+                    state.swallowed.iter().for_each(|&(_, terminal)| {
+                        xws.request_window_events(terminal);
+                    });
+
                     return Ok(state);
                 },
                 Err(e) => error!("failed to restore previous state: {}", e)
@@ -55,13 +100,26 @@ impl WmState {
                         screen: c.screen,
                         index: idx,
                         managed: Stack::new(Some(c.layout)),
+                        project: c.project,
                         focus: idx == 0,
                         ..Default::default()
                     }
                 })
                 .collect(),
             unmanaged: Vec::new(),
+            desktops: Vec::new(),
+            swallowed: Vec::new(),
+            scratchpads: Vec::new(),
+            focus_history: Vec::new(),
+            history_cursor: None,
+            spawns: Vec::new(),
+            pings: Vec::new(),
+            hung: Vec::new(),
+            hung_changed: false,
+            menu: None,
+            showing_desktop: false,
             cur: 0,
+            prev: None,
             screens: Vec::new(),
         })
     }
@@ -130,35 +188,291 @@ impl WmState {
     }
 
     pub fn set_urgency(&mut self, is_urgent: bool, window: Window) {
-        if let Some(ws) = self.get_parent_mut(window) {
-            ws.set_urgency(is_urgent, window);
+        for idx in self.copies(window) {
+            self.workspaces[idx].set_urgency(is_urgent, window);
+        }
+    }
+
+    // finds the managed terminal that started the process owning the window
+    fn find_swallow_target(&self, xws: &XlibWindowSystem, config: &Config, window: Window) -> Option<Window> {
+        let is_terminal = |w: Window| {
+            xws.get_class_name(w)
+                .map(|class| config.swallow_classes.contains(&class))
+                .unwrap_or(false)
+        };
+
+        if config.swallow_classes.is_empty() || is_terminal(window) {
+            return None;
+        }
+
+        let ancestors = utils::process_ancestors(ewmh::get_wm_pid(xws, window)?);
+
+        self.workspaces
+            .iter()
+            .flat_map(|ws| ws.all())
+            .filter(|&w| is_terminal(w))
+            .find(|&w| {
+                ewmh::get_wm_pid(xws, w)
+                    .map(|pid| ancestors.contains(&pid))
+                    .unwrap_or(false)
+            })
+    }
+
+    fn swallow_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, terminal: Window) {
+        debug!("{:#x} swallows {:#x}", window, terminal);
+        let screens = self.screens.clone();
+        let copies = self.copies(terminal);
+        if copies.is_empty() {
+            return;
+        }
+
+        xws.hide_window(terminal);
+
+        // not shown anywhere yet, but it is managed on the first copy's workspace
+        if !copies.iter().any(|&idx| self.workspaces[idx].is_visible()) {
+            xws.hide_window(window);
+            ewmh::set_wm_desktop(xws, window, copies[0]);
+        }
+
+        // every copy of the terminal is replaced
+        for idx in copies {
+            let workspace = &mut self.workspaces[idx];
+            workspace.replace_window(terminal, window);
+
+            if workspace.is_visible() {
+                workspace.redraw(xws, config, &screens);
+                xws.show_window(window);
+                ewmh::set_wm_desktop(xws, window, workspace.index);
+            }
+        }
+
+        self.swallowed.push((window, terminal));
+        self.raise_sticky(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+    }
+
+    // gives the place of a closed window back to the terminal it swallowed
+    fn release_swallowed(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) -> bool {
+        let Some(idx) = self.swallowed.iter().position(|&(w, _)| w == window) else {
+            return false;
+        };
+
+        let (_, terminal) = self.swallowed.remove(idx);
+        let screens = self.screens.clone();
+        let copies = self.copies(window);
+        if copies.is_empty() {
+            return true;
+        }
+
+        debug!("{:#x} releases {:#x}", window, terminal);
+        xws.hide_window(window);
+
+        for idx in copies {
+            let workspace = &mut self.workspaces[idx];
+            workspace.replace_window(window, terminal);
+
+            if workspace.is_visible() {
+                xws.show_window(terminal);
+                ewmh::set_wm_desktop(xws, terminal, workspace.index);
+                workspace.redraw(xws, config, &screens);
+            }
+        }
+
+        if self.current_ws().focused_window() == Some(terminal) {
+            xws.focus_window(terminal);
+        }
+
+        self.raise_sticky(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+        true
+    }
+
+    // drops all references to a window that was destroyed or withdrawn by its client.
+    // Windows that only move between workspaces keep them
+    pub fn forget_window(&mut self, window: Window) {
+        self.forget_ping(window);
+        self.unmanaged.retain(|&w| w != window);
+        self.desktops.retain(|&w| w != window);
+
+        if self.focus_history.contains(&window) {
+            self.focus_history.retain(|&w| w != window);
+            self.history_cursor = None;
+        }
+
+        self.swallowed.retain(|&(_, terminal)| terminal != window);
+        self.scratchpads.retain(|&(_, w)| w != window);
+    }
+
+    fn match_scratchpad(&self, xws: &XlibWindowSystem, config: &Config, window: Window) -> Option<String> {
+        config.scratchpads
+            .iter()
+            .filter(|x| !self.scratchpads.iter().any(|(name, _)| *name == x.name))
+            .find(|x| x.rule.matches(xws, window))
+            .map(|x| x.name.clone())
+    }
+
+    // moves the scratchpad window between its hidden state and the current workspace.
+    // Returns false if there is no window for the scratchpad yet
+    pub fn toggle_scratchpad(&mut self, xws: &XlibWindowSystem, config: &Config, name: &str) -> bool {
+        let Some(window) = self.scratchpads
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|&(_, w)| w)
+        else {
+            return false;
+        };
+
+        let is_current = self.current_ws().contains(window);
+
+        // a visible but unfocused scratchpad only gets focused instead of hidden
+        if is_current && self.current_ws().focused_window() != Some(window) {
+            self.focus_window(xws, config, window, false);
+            return true;
+        }
+
+        if self.contains(window) {
+            self.detach_window(xws, config, window);
+        }
+
+        if !is_current {
+            let index = self.cur;
+            self.current_ws_mut().add_floating(window);
+            self.redraw_current(xws, config);
+            xws.show_window(window);
+            ewmh::set_wm_desktop(xws, window, index);
+            self.focus_window(xws, config, window, false);
+        }
+
+        ewmh::set_client_list(xws, &self.workspaces);
+        true
+    }
+
+    // the first window type with a configured policy decides how the window is handled
+    fn window_policy(xws: &XlibWindowSystem, config: &Config, window: Window) -> WindowPolicy {
+        let policy = ewmh::get_window_types(xws, window)
+            .iter()
+            .find_map(|x| config.window_policies.get(x).copied())
+            .unwrap_or(WindowPolicy::Tile);
+
+        if policy == WindowPolicy::Tile && xws.is_floating_window(window) {
+            WindowPolicy::Float
+        } else {
+            policy
+        }
+    }
+
+    // the bounding box of all screens
+    fn desktop_rect(&self) -> Rect {
+        let x = self.screens.iter().map(|s| s.x).min().unwrap_or(0);
+        let y = self.screens.iter().map(|s| s.y).min().unwrap_or(0);
+        let width = self.screens.iter().map(|s| s.x + s.width).max().unwrap_or(0);
+        let height = self.screens.iter().map(|s| s.y + s.height).max().unwrap_or(0);
+
+        Rect { x, y, width: width - x, height: height - y }
+    }
+
+    fn add_overlay(&mut self, xws: &XlibWindowSystem, window: Window) {
+        debug!("Add Overlay: {:#x}", window);
+        if !self.unmanaged.contains(&window) {
+            self.unmanaged.push(window);
         }
+
+        xws.show_window(window);
+        xws.raise_window(window);
+    }
+
+    fn add_desktop(&mut self, xws: &XlibWindowSystem, window: Window) {
+        debug!("Add Desktop: {:#x}", window);
+        if !self.desktops.contains(&window) {
+            self.desktops.push(window);
+        }
+
+        let rect = self.desktop_rect();
+        xws.setup_window(rect.x, rect.y, rect.width, rect.height, 0, 0, window);
+        xws.show_window(window);
+        xws.lower_window(window);
+    }
+
+    // centres a floating window over its transient parent
+    fn rect_over_parent(xws: &XlibWindowSystem, config: &Config, window: Window, parent: Window) -> Rect {
+        let border = 2 * config.border_width;
+        let parent_rect = xws.get_geometry(parent);
+        let mut rect = xws.get_geometry(window);
+
+        rect.width += border;
+        rect.height += border;
+        rect.x = (parent_rect.x + (parent_rect.width + border) / 2).saturating_sub(rect.width / 2);
+        rect.y = (parent_rect.y + (parent_rect.height + border) / 2).saturating_sub(rect.height / 2);
+        rect
     }
 
     pub fn add_window(&mut self, index: Option<usize>, xws: &XlibWindowSystem, config: &Config, window: Window) {
         if !self.contains(window) {
             let screens = self.screens.clone();
+            let policy = Self::window_policy(xws, config, window);
+
+            match policy {
+                WindowPolicy::Overlay => return self.add_overlay(xws, window),
+                WindowPolicy::Desktop => return self.add_desktop(xws, window),
+                _ => (),
+            }
+
+            let transient = xws.transient_for(window)
+                .filter(|&x| self.contains(x));
+            let parent = transient.and_then(|x| self.find_window(x));
+
+            let scratchpad = self.match_scratchpad(xws, config, window);
 
-            let parent = xws.transient_for(window)
-                .and_then(|x| self.find_window(x));
+            if parent.is_none()
+                && scratchpad.is_none()
+                && policy == WindowPolicy::Tile
+                && let Some(terminal) = self.find_swallow_target(xws, config, window) {
+                    self.swallow_window(xws, config, window, terminal);
+                    return;
+            }
+
+            // scratchpads always show up on the current workspace
+            let is_scratchpad = scratchpad.is_some();
+            let index = if is_scratchpad { None } else { index };
+
+            if let Some(name) = scratchpad {
+                debug!("scratchpad {}: {:#x}", name, window);
+                self.scratchpads.push((name, window));
+            }
 
             let workspace = parent
                 .or_else(|| index.or_else(|| Some(self.get_ws_index())))
                 .and_then(|idx| self.get_ws_mut(idx))
                 .expect("valid workspace");
 
-            workspace.add_window(xws, window);
+            if is_scratchpad || policy == WindowPolicy::Float {
+                workspace.add_floating(window);
+            } else {
+                workspace.add_window(xws, window);
+            }
+
+            if policy == WindowPolicy::Float
+                && let Some(transient) = transient {
+                    workspace.set_floating_rect(window, Self::rect_over_parent(xws, config, window, transient));
+            }
+
+            ewmh::set_frame_extents(xws, window, config.border_width);
 
             if parent.is_some() {
                 workspace.focus_window(xws, window);
             }
 
             if !workspace.is_visible() {
+                // managed but not shown yet
+                xws.hide_window(window);
+                ewmh::set_wm_desktop(xws, window, workspace.index);
                 workspace.set_urgency(true, window);
             } else {
                 //workspace.focus_window(xws, window);
                 workspace.redraw(xws, config, &screens);
-                xws.show_window(window);
+                if !workspace.managed.hidden_windows().contains(&window) {
+                    xws.show_window(window);
+                }
                 self.raise_sticky(xws);
             }
 
@@ -166,9 +480,52 @@ impl WmState {
         }
     }
 
+    pub fn record_focus(&mut self, window: Window) {
+        // windows focused while cycling through the history keep their place
+        if self.history_cursor.and_then(|idx| self.focus_history.get(idx)) == Some(&window) {
+            return;
+        }
+
+        self.history_cursor = None;
+        self.focus_history.retain(|&w| w != window);
+        self.focus_history.insert(0, window);
+    }
+
+    pub fn focus_previous(&mut self, xws: &XlibWindowSystem, config: &Config) {
+        if let Some(&window) = self.focus_history.get(1) {
+            self.focus_window(xws, config, window, true);
+        }
+    }
+
+    // walks through the history without reordering it until another window gets focused
+    pub fn cycle_focus_history(&mut self, xws: &XlibWindowSystem, config: &Config, backwards: bool) {
+        let len = self.focus_history.len();
+        if len < 2 {
+            return;
+        }
+
+        let idx = self.history_cursor.unwrap_or(0);
+        let idx = if backwards { (idx + len - 1) % len } else { (idx + 1) % len };
+
+        self.history_cursor = Some(idx);
+        self.focus_window(xws, config, self.focus_history[idx], true);
+    }
+
     pub fn focus_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, force_switch: bool) {
-        if xws.get_wm_hints(window).map(|x| x.input != 0).unwrap_or(true)
+        if self.contains(window) {
+            self.show_desktop(xws, config, false);
+        }
+
+        self.restore_window(xws, config, window);
+
+        if self.contains(window) {
+            self.ping_window(xws, window);
+        }
+
+        if xws.accepts_focus(window)
             && let Some(index) = self.find_window(window) {
+                self.record_focus(window);
+
                 if self.cur != index {
                     let workspace = self.get_ws_mut(index)
                         .expect("valid workspace");
@@ -179,7 +536,14 @@ impl WmState {
                         self.switch_to_ws(xws, config, index, false);
                     }
                 } else {
+                    let is_hidden = self.current_ws().managed.hidden_windows().contains(&window);
                     self.current_ws_mut().focus_window(xws, window);
+
+                    // the window was unmapped by the layout and can only take focus once it is shown again
+                    if is_hidden {
+                        self.redraw_current(xws, config);
+                        xws.focus_window(window);
+                    }
                 }
         }
     }
@@ -189,6 +553,8 @@ impl WmState {
             return
         }
 
+        self.show_desktop(xws, config, false);
+
         // implies that the target workspace is on another screen
         if self.workspaces[index].visible {
             if config.greedy_view {
@@ -202,17 +568,306 @@ impl WmState {
             self.workspaces[index].screen = self.workspaces[self.cur].screen;
             self.workspaces[index].redraw(xws, config, &self.screens);
             self.workspaces[index].show(xws);
-            self.workspaces[self.cur].hide(xws);
+
+            let keep = self.shown_elsewhere(self.cur);
+            self.workspaces[self.cur].hide(xws, &keep);
         }
 
         self.workspaces[self.cur].unfocus(xws, config);
         self.workspaces[index].focus(xws);
 
+        self.prev = Some(self.cur);
         self.cur = index;
 
         self.raise_sticky(xws);
         ewmh::set_current_desktop(xws, index);
         ewmh::set_desktop_viewport(xws, self.all_ws());
+        ewmh::set_workarea(xws, self.all_ws());
+
+        self.start_project(index);
+    }
+
+    // spawns the startup commands of a project workspace on its first visit while empty
+    pub fn start_project(&mut self, index: usize) {
+        if let Some(ws) = self.workspaces.get_mut(index)
+            && !ws.project_started
+            && ws.is_empty()
+            && let Some(project) = ws.project.as_ref() {
+                debug!("starting project {}", ws.tag);
+
+                for (cmd, args) in project.startup.iter() {
+                    if let Some(pid) = utils::exec_in(cmd.clone(), args.clone(), Some(project.dir.clone()), project.envs.clone()) {
+                        self.spawns.push((pid, index));
+                    }
+                }
+
+                ws.project_started = true;
+        }
+    }
+
+    // at most one ping per window is in flight
+    pub fn ping_window(&mut self, xws: &XlibWindowSystem, window: Window) {
+        if !self.pings.iter().any(|&(w, _)| w == window) && xws.ping_window(window) {
+            self.pings.push((window, Instant::now()));
+        }
+    }
+
+    pub fn pong(&mut self, window: Window) {
+        self.pings.retain(|&(w, _)| w != window);
+
+        if self.hung.contains(&window) {
+            debug!("{:#x} is responding again", window);
+            self.hung.retain(|&w| w != window);
+            self.hung_changed = true;
+        }
+    }
+
+    fn forget_ping(&mut self, window: Window) {
+        self.pings.retain(|&(w, _)| w != window);
+
+        if self.hung.contains(&window) {
+            self.hung.retain(|&w| w != window);
+            self.hung_changed = true;
+        }
+    }
+
+    pub fn is_hung(&self, window: Window) -> bool {
+        self.hung.contains(&window)
+    }
+
+    // time left until the oldest ping runs out
+    pub fn ping_deadline(&self, timeout: Duration) -> Option<Duration> {
+        self.pings
+            .iter()
+            .map(|&(_, sent)| timeout.saturating_sub(sent.elapsed()))
+            .min()
+    }
+
+    // marks clients that missed the timeout as hung. Returns true if the
+    // set of hung clients changed since the last call
+    pub fn expire_pings(&mut self, timeout: Duration) -> bool {
+        let (expired, pending): (Vec<_>, Vec<_>) = self.pings
+            .iter()
+            .partition(|&&(_, sent)| sent.elapsed() >= timeout);
+
+        self.pings = pending;
+        for (window, _) in expired {
+            debug!("{:#x} is not responding", window);
+            if !self.hung.contains(&window) {
+                self.hung.push(window);
+                self.hung_changed = true;
+            }
+        }
+
+        mem::take(&mut self.hung_changed)
+    }
+
+    pub fn has_menu(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    pub fn set_menu(&mut self, stream: UnixStream, action: MenuAction) {
+        self.menu = Some((stream, action));
+    }
+
+    pub fn take_menu(&mut self) -> Option<(UnixStream, MenuAction)> {
+        self.menu.take()
+    }
+
+    // the event loop waits on this to learn that the menu program exited
+    pub fn menu_fd(&self) -> Option<RawFd> {
+        self.menu.as_ref().map(|(stream, _)| stream.as_raw_fd())
+    }
+
+    pub fn add_spawn(&mut self, pid: u64, index: usize) {
+        self.spawns.push((pid, index));
+    }
+
+    // returns the workspace a window should be placed on if it belongs to a
+    // process spawned by the wm or one of its descendants
+    pub fn take_spawn_target(&mut self, xws: &XlibWindowSystem, window: Window) -> Option<usize> {
+        self.spawns.retain(|&(pid, _)| utils::process_exists(pid));
+
+        let pid = ewmh::get_wm_pid(xws, window)?;
+        let ancestors = utils::process_ancestors(pid);
+
+        let idx = self.spawns
+            .iter()
+            .position(|&(x, _)| x == pid || ancestors.contains(&x))?;
+
+        let (_, index) = self.spawns.remove(idx);
+        Some(index).filter(|&x| x < self.ws_count())
+    }
+
+    // publishes the workspace list after it has been changed at runtime
+    fn update_desktops(&self, xws: &XlibWindowSystem) {
+        ewmh::set_number_of_desktops(xws, self.ws_count());
+        ewmh::set_desktop_names(xws, self.all_ws());
+        ewmh::set_desktop_viewport(xws, self.all_ws());
+        ewmh::set_workarea(xws, self.all_ws());
+        ewmh::set_current_desktop(xws, self.cur);
+        ewmh::set_client_list(xws, &self.workspaces);
+
+        for ws in self.all_visible_ws() {
+            for window in ws.all() {
+                ewmh::set_wm_desktop(xws, window, ws.index);
+            }
+        }
+    }
+
+    // fixes workspace indices after the list got reordered. The mapping
+    // returns the new index of a workspace or None if it got removed
+    fn reindex<F: Fn(usize) -> Option<usize>>(&mut self, map: F) {
+        for (idx, ws) in self.workspaces.iter_mut().enumerate() {
+            ws.index = idx;
+        }
+
+        self.cur = map(self.cur).unwrap_or(0);
+        self.prev = self.prev.and_then(&map);
+        self.spawns = self.spawns
+            .iter()
+            .filter_map(|&(pid, idx)| map(idx).map(|x| (pid, x)))
+            .collect();
+    }
+
+    pub fn add_ws(&mut self, xws: &XlibWindowSystem, tag: &str, layout: Box<dyn Layout>) -> usize {
+        let index = self.workspaces.len();
+        debug!("adding workspace {}: {}", index, tag);
+
+        self.workspaces.push(Workspace {
+            tag: tag.to_string(),
+            screen: self.current_ws().get_screen(),
+            index,
+            managed: Stack::new(Some(layout)),
+            ..Default::default()
+        });
+
+        // sticky windows show up on every workspace
+        for window in self.all_windows().into_iter().filter(|&w| ewmh::is_window_sticky(xws, w)) {
+            let is_floating = self.is_floating(window);
+            let ws = &mut self.workspaces[index];
+
+            if is_floating {
+                ws.add_floating(window);
+            } else {
+                ws.add_window(xws, window);
+            }
+        }
+
+        self.update_desktops(xws);
+        index
+    }
+
+    pub fn rename_ws(&mut self, xws: &XlibWindowSystem, index: usize, tag: &str) {
+        if let Some(ws) = self.workspaces.get_mut(index)
+            && ws.tag != tag {
+                debug!("renaming workspace {}: {} -> {}", index, ws.tag, tag);
+                ws.tag = tag.to_string();
+                ewmh::set_desktop_names(xws, self.all_ws());
+        }
+    }
+
+    // windows that live on no other workspace. Copies of sticky windows do not count
+    fn has_own_windows(&self, index: usize) -> bool {
+        self.workspaces[index]
+            .all()
+            .iter()
+            .any(|&w| self.copies(w).len() < 2)
+    }
+
+    // only empty workspaces can be removed, copies of windows on other workspaces are
+    // dropped with it. The current workspace is swapped for a hidden one first and
+    // visible workspaces on other screens are kept
+    pub fn remove_ws(&mut self, xws: &XlibWindowSystem, config: &Config, index: usize) -> bool {
+        if index >= self.ws_count() || self.ws_count() < 2 || self.has_own_windows(index) {
+            return false;
+        }
+
+        if index == self.cur {
+            let target = self.prev
+                .filter(|&idx| !self.workspaces[idx].is_visible())
+                .or_else(|| self.workspaces.iter().position(|ws| !ws.is_visible()));
+
+            match target {
+                Some(target) => self.switch_to_ws(xws, config, target, false),
+                None => return false,
+            }
+        } else if self.workspaces[index].is_visible() {
+            return false;
+        }
+
+        debug!("removing workspace {}: {}", index, self.workspaces[index].tag);
+        self.workspaces.remove(index);
+        self.reindex(|idx| match idx {
+            x if x == index => None,
+            x if x > index => Some(x - 1),
+            x => Some(x),
+        });
+
+        self.update_desktops(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+        true
+    }
+
+    pub fn move_ws(&mut self, xws: &XlibWindowSystem, from: usize, to: usize) {
+        let len = self.ws_count();
+        if from >= len || to >= len || from == to {
+            return;
+        }
+
+        debug!("moving workspace {} to {}", from, to);
+        let ws = self.workspaces.remove(from);
+        self.workspaces.insert(to, ws);
+
+        self.reindex(|idx| Some(match idx {
+            x if x == from => to,
+            x if from < to && x > from && x <= to => x - 1,
+            x if to < from && x >= to && x < from => x + 1,
+            x => x,
+        }));
+
+        self.update_desktops(xws);
+    }
+
+    // applies desktop names changed by a pager
+    pub fn sync_desktop_names(&mut self, xws: &XlibWindowSystem) {
+        let names = xws.get_string_list(xws.get_root_window(), "_NET_DESKTOP_NAMES");
+        let mut changed = false;
+
+        for (ws, name) in self.workspaces.iter_mut().zip(names) {
+            if ws.tag != name {
+                debug!("renaming workspace {}: {} -> {}", ws.index, ws.tag, name);
+                ws.tag = name;
+                changed = true;
+            }
+        }
+
+        if changed {
+            ewmh::set_desktop_names(xws, self.all_ws());
+        }
+    }
+
+    pub fn find_relative_ws(&self, mode: CycleMode, forward: bool) -> Option<usize> {
+        let len = self.workspaces.len();
+        let screen = self.current_ws().get_screen();
+
+        (1..len)
+            .map(|offset| if forward { (self.cur + offset) % len } else { (self.cur + len - offset) % len })
+            .find(|&idx| {
+                let ws = &self.workspaces[idx];
+                match mode {
+                    CycleMode::All => true,
+                    CycleMode::NonEmpty => !ws.is_empty(),
+                    CycleMode::Hidden => !ws.is_visible(),
+                    CycleMode::Screen => ws.get_screen() == screen,
+                }
+            })
+    }
+
+    pub fn switch_to_prev_ws(&mut self, xws: &XlibWindowSystem, config: &Config) {
+        if let Some(index) = self.prev {
+            self.switch_to_ws(xws, config, index, true);
+        }
     }
 
     pub fn switch_to_ws_at(&mut self, xws: &XlibWindowSystem, config: &Config, x: u32, y: u32, center_pointer: bool) {
@@ -248,32 +903,314 @@ impl WmState {
             self.workspaces[index].focus(xws);
 
             self.workspaces[index].center_pointer(xws);
+            self.prev = Some(self.cur);
             self.cur = index;
 
             ewmh::set_current_desktop(xws, index);
+            self.start_project(index);
         }
     }
 
     pub fn move_window_to_ws(&mut self, xws: &XlibWindowSystem, config: &Config, index: usize) {
-        if index == self.cur || index >= self.ws_count() {
+        if let Some(window) = self.current_ws().focused_window() {
+            self.send_window_to_ws(xws, config, window, index);
+        }
+    }
+
+    pub fn send_window_to_ws(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, index: usize) {
+        if index >= self.ws_count() || self.workspaces[index].contains(window) {
             return;
         }
 
-        if let Some(window) = self.current_ws().focused_window() {
-            self.remove_window(xws, config, window);
+        let marks = self.get_parent_mut(window)
+            .map(|ws| ws.take_marks(window))
+            .unwrap_or_default();
+        let is_floating = self.is_floating(window);
 
-            let ws = &mut self.workspaces[index];
+        // only the copy on the current workspace is moved
+        if !self.remove_copy(xws, config, window) {
+            self.detach_window(xws, config, window);
+        }
+
+        let ws = &mut self.workspaces[index];
+        if is_floating {
+            ws.add_floating(window);
+        } else {
             ws.add_window(xws, window);
-            ws.focus_window(xws, window);
+        }
+        marks.iter().for_each(|x| ws.add_mark(x, window));
+        ws.focus_window(xws, window);
+        ws.redraw(xws, config, &self.screens);
+
+        if ws.is_visible() {
+            xws.show_window(window);
+        }
+        self.raise_sticky(xws);
+
+        ewmh::set_wm_desktop(xws, window, index);
+        if self.current_ws().is_empty() {
+            ewmh::set_active_window(xws, 0);
+        }
+    }
+
+    pub fn all_windows(&self) -> Vec<Window> {
+        let mut windows: Vec<Window> = Vec::new();
+
+        for window in self.workspaces.iter().flat_map(|ws| ws.all()) {
+            if !windows.contains(&window) {
+                windows.push(window);
+            }
+        }
+
+        windows
+    }
+
+    // indices of all workspaces holding a copy of the window
+    pub fn copies(&self, window: Window) -> Vec<usize> {
+        self.workspaces
+            .iter()
+            .filter(|ws| ws.contains(window))
+            .map(|ws| ws.index)
+            .collect()
+    }
+
+    // windows on visible workspaces other than the given one
+    fn shown_elsewhere(&self, index: usize) -> Vec<Window> {
+        self.workspaces
+            .iter()
+            .filter(|ws| ws.index != index && ws.is_visible())
+            .flat_map(|ws| ws.all().into_iter().filter(|&w| !ws.is_minimized(w)))
+            .collect()
+    }
+
+    pub fn copy_window_to_ws(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, index: usize) {
+        if index >= self.ws_count() || self.workspaces[index].contains(window) {
+            return;
+        }
+
+        let Some(src) = self.find_window(window) else {
+            return;
+        };
+
+        debug!("copying {:#x} to workspace {}", window, index);
+        let is_floating = self.workspaces[src].is_floating(window);
+        let ws = &mut self.workspaces[index];
+
+        if is_floating {
+            ws.add_floating(window);
+        } else {
+            ws.add_window(xws, window);
+        }
+
+        if ws.is_visible() {
             ws.redraw(xws, config, &self.screens);
+            xws.show_window(window);
             self.raise_sticky(xws);
+        }
 
-            ewmh::set_wm_desktop(xws, window, index);
-            if self.current_ws().is_empty() {
-                ewmh::set_active_window(xws, 0);
+        ewmh::set_client_list(xws, &self.workspaces);
+    }
+
+    pub fn copy_window_to_all(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) {
+        for index in 0..self.ws_count() {
+            self.copy_window_to_ws(xws, config, window, index);
+        }
+    }
+
+    // removes the window from the current workspace only. Returns false if
+    // there is no copy on the current workspace or it is the last one
+    pub fn remove_copy(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) -> bool {
+        if !self.current_ws().contains(window) || self.copies(window).len() < 2 {
+            return false;
+        }
+
+        self.detach_from_ws(xws, config, window, self.cur);
+        self.raise_sticky(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+        true
+    }
+
+    // keeps the copy on the current workspace, or the first one if there is none
+    pub fn remove_other_copies(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) {
+        let copies = self.copies(window);
+        let keep = if copies.contains(&self.cur) { Some(self.cur) } else { copies.first().copied() };
+
+        for index in copies.into_iter().filter(|&x| Some(x) != keep) {
+            self.detach_from_ws(xws, config, window, index);
+        }
+
+        self.raise_sticky(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+    }
+
+    pub fn find_windows_by_class(&self, xws: &XlibWindowSystem, class: &str) -> Vec<Window> {
+        self.all_windows()
+            .into_iter()
+            .filter(|&w| xws.get_class_name(w).as_deref() == Some(class))
+            .collect()
+    }
+
+    pub fn find_mark(&self, mark: &str) -> Option<Window> {
+        self.workspaces.iter().find_map(|ws| ws.get_marked(mark))
+    }
+
+    // marks are unique and move to the new window if they are already in use
+    pub fn mark_window(&mut self, window: Window, mark: &str) {
+        self.workspaces.iter_mut().for_each(|ws| ws.remove_mark(mark));
+
+        if let Some(ws) = self.get_parent_mut(window) {
+            ws.add_mark(mark, window);
+        }
+    }
+
+    pub fn swap_windows(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, other: Window) {
+        let (Some(idx), Some(other_idx)) = (self.find_window(window), self.find_window(other)) else {
+            return;
+        };
+
+        if window == other {
+            return;
+        }
+
+        if idx == other_idx {
+            self.workspaces[idx].swap_windows(window, other);
+        } else {
+            let marks = self.workspaces[idx].take_marks(window);
+            let other_marks = self.workspaces[other_idx].take_marks(other);
+            let is_urgent = self.workspaces[idx].is_window_urgent(window);
+            let other_is_urgent = self.workspaces[other_idx].is_window_urgent(other);
+
+            self.workspaces[idx].replace_window(window, other);
+            self.workspaces[other_idx].replace_window(other, window);
+
+            other_marks.iter().for_each(|x| self.workspaces[idx].add_mark(x, other));
+            marks.iter().for_each(|x| self.workspaces[other_idx].add_mark(x, window));
+
+            if other_is_urgent {
+                self.workspaces[idx].set_urgency(true, other);
+            }
+            if is_urgent {
+                self.workspaces[other_idx].set_urgency(true, window);
+            }
+
+            for (ws_idx, w) in [(idx, other), (other_idx, window)] {
+                if self.workspaces[ws_idx].is_visible() {
+                    xws.show_window(w);
+                    ewmh::set_wm_desktop(xws, w, ws_idx);
+                } else {
+                    xws.hide_window(w);
+                }
             }
         }
 
+        self.redraw(xws, config);
+
+        if let Some(w) = self.current_ws().focused_window() {
+            xws.focus_window(w);
+        }
+    }
+
+    pub fn move_window_to_mark(&mut self, xws: &XlibWindowSystem, config: &Config, mark: &str) {
+        let Some(window) = self.current_ws().focused_window() else {
+            return;
+        };
+
+        match self.find_mark(mark) {
+            Some(target) if target != window => {
+                let marks = self.current_ws_mut().take_marks(window);
+                if !self.remove_copy(xws, config, window) {
+                    self.detach_window(xws, config, window);
+                }
+
+                if let Some(index) = self.find_window(target) {
+                    let ws = &mut self.workspaces[index];
+                    ws.insert_after(target, window);
+                    marks.iter().for_each(|x| ws.add_mark(x, window));
+                    ws.focus_window(xws, window);
+
+                    if ws.is_visible() {
+                        ws.redraw(xws, config, &self.screens);
+                        xws.show_window(window);
+                    }
+                    ewmh::set_wm_desktop(xws, window, index);
+
+                    self.raise_sticky(xws);
+                    ewmh::set_client_list(xws, &self.workspaces);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // tiled windows are only turned into floating ones if the config allows it
+    fn make_floating(&mut self, config: &Config, window: Window) -> bool {
+        if self.is_floating(window) {
+            return true;
+        }
+
+        if !config.float_on_moveresize || !self.contains(window) {
+            return false;
+        }
+
+        debug!("floating {:#x}", window);
+        for ws in self.workspaces.iter_mut() {
+            ws.float_window(window);
+        }
+
+        true
+    }
+
+    fn set_floating_rect(&mut self, window: Window, rect: Rect) {
+        for ws in self.workspaces.iter_mut().filter(|ws| ws.is_floating(window)) {
+            ws.set_floating_rect(window, rect);
+        }
+    }
+
+    // applies the geometry requested by the client. The size excludes the border
+    pub fn move_resize_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window,
+                              (x, y): (Option<i32>, Option<i32>), (width, height): (Option<u32>, Option<u32>)) {
+        if !self.make_floating(config, window) {
+            return;
+        }
+
+        let border = 2 * config.border_width;
+        let mut rect = xws.get_geometry(window);
+        let screen = self.find_window(window)
+            .and_then(|idx| self.screens.get(self.workspaces[idx].get_screen()).copied())
+            .unwrap_or_else(|| self.desktop_rect());
+
+        // the size comes from the client and may be anything up to u32::MAX
+        rect.x = x.map(|x| cmp::max(x, 0) as u32).unwrap_or(rect.x);
+        rect.y = y.map(|y| cmp::max(y, 0) as u32).unwrap_or(rect.y);
+        rect.width = cmp::min(width.unwrap_or(rect.width).saturating_add(border), screen.width);
+        rect.height = cmp::min(height.unwrap_or(rect.height).saturating_add(border), screen.height);
+
+        self.set_floating_rect(window, rect);
+        self.redraw(xws, config);
+    }
+
+    // moves or resizes the window with the pointer until the button is released
+    pub fn drag_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, drag: Drag) {
+        if !self.make_floating(config, window) {
+            return;
+        }
+
+        self.redraw(xws, config);
+
+        let border = 2 * config.border_width;
+        let mut start = xws.get_geometry(window);
+        start.width += border;
+        start.height += border;
+
+        let mut rect = start;
+        xws.raise_window(window);
+        xws.track_pointer(|dx, dy| {
+            rect = drag.apply(start, dx, dy);
+            xws.setup_window(rect.x, rect.y, rect.width, rect.height, config.border_width, config.border_focus_color, window);
+        });
+
+        self.set_floating_rect(window, rect);
+        self.redraw(xws, config);
     }
 
     pub fn move_window_to_screen(&mut self, xws: &XlibWindowSystem, config: &Config, screen: usize) {
@@ -286,26 +1223,167 @@ impl WmState {
         }
     }
 
+    // unmanages a destroyed or withdrawn window. Moves between workspaces use detach_window
+    // instead, which keeps the swallowed terminal hidden
     pub fn remove_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) {
+        if self.release_swallowed(xws, config, window) {
+            return;
+        }
+
+        self.detach_window(xws, config, window);
+    }
+
+    fn detach_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) {
+        let copies = self.copies(window);
+        if copies.is_empty() {
+            return;
+        }
+
+        for index in copies {
+            self.detach_from_ws(xws, config, window, index);
+        }
+
+        self.raise_sticky(xws);
+        ewmh::set_client_list(xws, &self.workspaces);
+    }
+
+    fn detach_from_ws(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window, index: usize) {
+        let is_shown_elsewhere = self.shown_elsewhere(index).contains(&window);
+
+        if let Some(workspace) = self.workspaces.get_mut(index) {
+            let was_focused = workspace.focused_window() == Some(window);
+
+            if is_shown_elsewhere {
+                workspace.remove_copy(window);
+            } else {
+                workspace.remove_window(xws, window);
+            }
+
+            self.refocus_ws(xws, config, index, was_focused);
+        }
+    }
+
+    // updates the focus and layout of a workspace after a window left it
+    fn refocus_ws(&mut self, xws: &XlibWindowSystem, config: &Config, index: usize, was_focused: bool) {
         let screens = self.screens.clone();
+        let workspace = &mut self.workspaces[index];
+
+        // hand the focus to the most recently used window instead of the next one in the stack
+        if was_focused
+            && let Some(&w) = self.focus_history
+                .iter()
+                .find(|&&w| workspace.contains(w) && !workspace.is_minimized(w)) {
+                workspace.set_focus(w);
+        }
 
-        if let Some(workspace) = self.get_parent_mut(window) {
-            workspace.remove_window(xws, window);
+        if workspace.is_visible() {
+            if let Some(w) = workspace.focused_window() {
+                xws.focus_window(w);
+            }
+            workspace.redraw(xws, config, &screens);
+        }
 
-            if workspace.is_visible() {
-                if let Some(w) = workspace.focused_window() {
-                    xws.focus_window(w);
-                }
+        if workspace.focused_window().is_none() {
+            ewmh::set_active_window(xws, 0);
+        }
+    }
+
+    pub fn is_minimized(&self, window: Window) -> bool {
+        self.workspaces.iter().any(|ws| ws.is_minimized(window))
+    }
+
+    pub fn is_showing_desktop(&self) -> bool {
+        self.showing_desktop
+    }
+
+    // hides the windows of all visible workspaces until the mode is left again.
+    // Focusing a window or switching workspaces leaves it as well
+    pub fn show_desktop(&mut self, xws: &XlibWindowSystem, config: &Config, showing: bool) {
+        if self.showing_desktop == showing {
+            return;
+        }
+
+        debug!("showing desktop: {}", showing);
+        self.showing_desktop = showing;
+        let screens = self.screens.clone();
+
+        for workspace in self.workspaces.iter_mut().filter(|ws| ws.is_visible()) {
+            if showing {
+                workspace.conceal(xws);
+            } else {
+                workspace.reveal(xws);
                 workspace.redraw(xws, config, &screens);
             }
+        }
 
-            if workspace.is_empty() {
-                ewmh::set_active_window(xws, 0);
+        if showing {
+            match self.desktops.first() {
+                Some(&desktop) => xws.focus_window(desktop),
+                None => ewmh::set_active_window(xws, 0),
             }
+        } else {
+            match self.current_ws().focused_window() {
+                Some(window) => xws.focus_window(window),
+                None => ewmh::set_active_window(xws, 0),
+            }
+            self.raise_sticky(xws);
+        }
+
+        ewmh::set_showing_desktop(xws, showing);
+    }
+
+    pub fn minimized_windows(&self) -> Vec<Window> {
+        self.workspaces
+            .iter()
+            .flat_map(|ws| ws.minimized_windows())
+            .collect()
+    }
+
+    // minimizes the copy find_window resolves to
+    pub fn minimize_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) {
+        let Some(index) = self.find_window(window) else {
+            return;
+        };
+
+        let is_shown_elsewhere = self.shown_elsewhere(index).contains(&window);
+        let workspace = &mut self.workspaces[index];
+        let was_focused = workspace.focused_window() == Some(window);
+
+        if !workspace.minimize(window) {
+            return;
+        }
+
+        debug!("minimizing {:#x}", window);
+        if !is_shown_elsewhere {
+            xws.hide_window(window);
+            ewmh::set_wm_desktop(xws, window, index);
+        }
+
+        ewmh::set_wm_state(xws, window, &[xws.get_atom("_NET_WM_STATE_HIDDEN")], ewmh::NET_WM_STATE_ADD);
+
+        self.refocus_ws(xws, config, index, was_focused);
+        self.raise_sticky(xws);
+    }
+
+    // puts a minimized window back into the layout of its workspace
+    pub fn restore_window(&mut self, xws: &XlibWindowSystem, config: &Config, window: Window) -> bool {
+        let Some(index) = self.workspaces.iter().position(|ws| ws.is_minimized(window)) else {
+            return false;
+        };
 
+        debug!("restoring {:#x}", window);
+        let workspace = &mut self.workspaces[index];
+        workspace.restore(xws, window);
+        ewmh::set_wm_state(xws, window, &[xws.get_atom("_NET_WM_STATE_HIDDEN")], ewmh::NET_WM_STATE_REMOVE);
+
+        if workspace.is_visible() {
+            workspace.redraw(xws, config, &self.screens);
+            xws.show_window(window);
+            ewmh::set_wm_desktop(xws, window, index);
             self.raise_sticky(xws);
-            ewmh::set_client_list(xws, &self.workspaces);
         }
+
+        true
     }
 /*
     pub fn hide_window(&mut self, window: Window) {
@@ -317,6 +1395,7 @@ impl WmState {
     }
 */
     pub fn rescreen(&mut self, xws: &XlibWindowSystem, config: &Config) {
+        self.show_desktop(xws, config, false);
         let n_old_screens = self.screens.len();
 
         let screens = xws.get_screen_infos();
@@ -339,9 +1418,15 @@ impl WmState {
 
         // move and hide workspaces if their screens got removed
         if n_new_screens < n_old_screens {
+            let keep: Vec<Window> = self.workspaces
+                .iter()
+                .filter(|x| x.is_visible() && x.screen < n_new_screens)
+                .flat_map(|x| x.all())
+                .collect();
+
             for workspace in self.workspaces.iter_mut().filter(|x| x.screen > n_new_screens - 1) {
                 workspace.screen = 0;
-                workspace.hide(xws);
+                workspace.hide(xws, &keep);
             }
         } else {
             trace!("finding workspace to rescreen");
@@ -360,15 +1445,33 @@ impl WmState {
         debug!("rescreen: {:?}", self.screens);
         self.redraw(xws, config);
 
+        let rect = self.desktop_rect();
+        for &window in self.desktops.iter() {
+            xws.setup_window(rect.x, rect.y, rect.width, rect.height, 0, 0, window);
+            xws.lower_window(window);
+        }
+
+        ewmh::set_desktop_geometry(xws);
+
         ewmh::set_desktop_viewport(xws, self.all_ws());
+        ewmh::set_workarea(xws, self.all_ws());
     }
 
+    // copied windows resolve to the current workspace, then to a visible one and
+    // only then to the first copy. Use copies() to reach all of them
     pub fn find_window(&self, window: Window) -> Option<usize> {
-        self.workspaces.iter().enumerate().find(|(_, workspace)| workspace.contains(window)).map(|(idx, _)| idx)
+        let copies = self.copies(window);
+
+        copies
+            .iter()
+            .find(|&&idx| idx == self.cur)
+            .or_else(|| copies.iter().find(|&&idx| self.workspaces[idx].is_visible()))
+            .or_else(|| copies.first())
+            .copied()
     }
 
     pub fn get_parent_mut(&mut self, window: Window) -> Option<&mut Workspace> {
-        self.workspaces.iter_mut().find(|workspace| workspace.contains(window))
+        self.find_window(window).map(|idx| &mut self.workspaces[idx])
     }
 
     fn switch_screens(&mut self, xws: &XlibWindowSystem, dest: usize) {
@@ -377,12 +1480,20 @@ impl WmState {
         self.workspaces[dest].screen = screen;
 
         ewmh::set_desktop_viewport(xws, self.all_ws());
+        ewmh::set_workarea(xws, self.all_ws());
     }
 
     pub fn raise_sticky(&self, xws: &XlibWindowSystem) {
         for w in &self.unmanaged {
             xws.raise_window(*w);
         }
+
+        for w in &self.desktops {
+            xws.lower_window(*w);
+        }
+
+        // every redraw ends here, so the stacking order is final
+        ewmh::set_client_list_stacking(xws, &self.workspaces);
     }
 
     pub fn redraw(&self, xws: &XlibWindowSystem, config: &Config) {
@@ -397,14 +1508,12 @@ impl WmState {
         self.raise_sticky(xws);
     }
 
-    pub fn add_unmanaged(&mut self, window: Window) {
-        println!("add sticky: {}", window);
-        if !self.unmanaged.contains(&window) {
-            self.unmanaged.push(window);
+    pub fn try_remove_unmanaged(&mut self, window: Window) -> bool {
+        if let Some(idx) = self.desktops.iter().position(|&x| x == window) {
+            self.desktops.swap_remove(idx);
+            return true;
         }
-    }
 
-    pub fn try_remove_unmanaged(&mut self, window: Window) -> bool {
         if let Some((idx,_)) = self.unmanaged.iter()
             .enumerate()
             .find(|&(_,&x)| x == window)
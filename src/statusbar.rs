@@ -3,6 +3,7 @@
 use crate::state::WmState;
 use crate::xlib_window_system::XlibWindowSystem;
 use crate::config::{PagerInfo, WorkspaceInfo};
+use crate::ewmh;
 use std::io::Write;
 use std::process::{Command, Child, Stdio};
 use anyhow::{anyhow, bail, Context, Result};
@@ -46,10 +47,24 @@ impl Statusbar {
                 .collect::<Vec<String>>()
                 .join(" ");
 
-            format!("{} | {} | {}\n",
+            let names = &info.layout_names;
+            let mut layout = std::iter::once(&names.outer)
+                .chain(names.nested.iter())
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(" / ");
+
+            if let Some(ref selected) = names.selected {
+                layout.push_str(&format!(" ({selected})"));
+            }
+
+            let hung = if info.window_hung { " (not responding)" } else { "" };
+
+            format!("{} | {} | {}{}\n",
                     workspaces,
-                    info.layout_names.join("/"),
-                    info.window_title)
+                    layout,
+                    info.window_title,
+                    hung)
         }))
     }
 
@@ -69,9 +84,7 @@ impl Statusbar {
         let layout_names = state
             .current_ws()
             .managed
-            .layout_iter()
-            .map(|x| x.name())
-            .collect();
+            .layout_iter();
 
         let output = (self.fn_format)(PagerInfo {
             workspaces: state.all_ws()
@@ -85,11 +98,27 @@ impl Statusbar {
                         current: i == state.get_ws_index(),
                         visible: x.is_visible(),
                         urgent: x.is_urgent(),
+                        windows: x.all()
+                            .into_iter()
+                            .filter(|&w| !ewmh::is_skipped(xws, w))
+                            .map(|w| xws.get_window_title(w))
+                            .collect(),
+                        minimized: x.minimized_windows()
+                            .into_iter()
+                            .filter(|&w| !ewmh::is_skipped(xws, w))
+                            .map(|w| xws.get_window_title(w))
+                            .collect(),
+                        hung: x.all()
+                            .into_iter()
+                            .filter(|&w| state.is_hung(w))
+                            .map(|w| xws.get_window_title(w))
+                            .collect(),
                     }
                 })
                 .collect(),
             layout_names,
             window_title: xws.get_window_title(state.current_ws().focused_window().unwrap_or(0)),
+            window_hung: state.current_ws().focused_window().is_some_and(|w| state.is_hung(w)),
         });
 
         let stdin = child
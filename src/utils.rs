@@ -1,6 +1,11 @@
 use std::process::{Command, Stdio};
 use std::env;
-use anyhow::{Error, Result};
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::thread;
+use anyhow::{anyhow, Context, Error, Result};
 
 #[allow(dead_code)]
 pub fn xmessage(msg: &str) -> Result<()> {
@@ -23,22 +28,126 @@ pub fn concat_error_chain(err: &Error) -> String {
     msgs.as_slice().join("\n")
 }
 
-pub fn exec(cmd: String, args: Vec<String>) {
-    if !cmd.is_empty() {
-        std::thread::spawn(move || {
-            match Command::new(&cmd)
-                .envs(env::vars())
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .args(&args)
-                .spawn()
-            {
-                Ok(mut child) => {
-                    child.wait().ok();
-                },
-                Err(e) => error!("failed to start \"{:?}\": {}", cmd, e),
+pub fn exec(cmd: String, args: Vec<String>) -> Option<u64> {
+    exec_in(cmd, args, None, Vec::new())
+}
+
+// like exec but runs the command from the given directory with additional environment variables
+pub fn exec_in(cmd: String, args: Vec<String>, dir: Option<PathBuf>, envs: Vec<(String, String)>) -> Option<u64> {
+    if cmd.is_empty() {
+        return None;
+    }
+
+    let mut command = Command::new(&cmd);
+
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    match command
+        .envs(env::vars())
+        .envs(envs)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .args(&args)
+        .spawn()
+    {
+        Ok(mut child) => {
+            let pid = u64::from(child.id());
+
+            // reap the child to avoid leaving a zombie behind
+            std::thread::spawn(move || {
+                child.wait().ok();
+            });
+
+            Some(pid)
+        },
+        Err(e) => {
+            error!("failed to start \"{:?}\": {}", cmd, e);
+            None
+        }
+    }
+}
+
+pub fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|x| x.trim().to_string())
+}
+
+pub fn process_exists(pid: u64) -> bool {
+    Path::new(&format!("/proc/{pid}")).exists()
+}
+
+// runs a dmenu compatible program in the background, so the event loop can still manage
+// its window. The selected line is written to the returned stream once the program exits
+pub fn menu(cmd: &str, args: &[String], lines: &[String]) -> Result<UnixStream> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context(format!("failed to execute '{cmd}'"))?;
+
+    let mut stdin = child.stdin
+        .take()
+        .ok_or_else(|| anyhow!("failed to get menu stdin"))?;
+
+    let (reader, mut writer) = UnixStream::pair()
+        .context("failed to create menu stream")?;
+    let input = lines.join("\n");
+
+    thread::spawn(move || {
+        if let Err(e) = stdin.write_all(input.as_bytes()) {
+            error!("failed to write to menu stdin: {}", e);
+        }
+        drop(stdin);
+
+        match child.wait_with_output() {
+            Ok(output) => writer.write_all(&output.stdout).ok(),
+            Err(e) => {
+                error!("failed to read menu output: {}", e);
+                None
             }
-        });
+        };
+    });
+
+    Ok(reader)
+}
+
+// reads the line selected in a menu started with `menu`
+pub fn menu_selection(mut stream: UnixStream) -> Result<Option<String>> {
+    let mut selection = String::new();
+    stream.read_to_string(&mut selection)
+        .context("failed to read menu selection")?;
+
+    let selection = selection.trim_end_matches('\n');
+    Ok(if selection.is_empty() { None } else { Some(selection.to_string()) })
+}
+
+fn parent_pid(pid: u64) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // the process name is wrapped in parentheses and may contain spaces itself
+    stat.rsplit_once(')')
+        .and_then(|(_, x)| x.split_whitespace().nth(1))
+        .and_then(|x| x.parse::<u64>().ok())
+}
+
+pub fn process_ancestors(pid: u64) -> Vec<u64> {
+    let mut ancestors = Vec::new();
+    let mut pid = pid;
+
+    while let Some(ppid) = parent_pid(pid) {
+        if ppid <= 1 || ancestors.contains(&ppid) {
+            break;
+        }
+
+        ancestors.push(ppid);
+        pid = ppid;
     }
+
+    ancestors
 }
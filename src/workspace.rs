@@ -5,6 +5,7 @@ use crate::layout::{LayoutMsg, Rect};
 use crate::stack::Stack;
 use crate::xlib_window_system::XlibWindowSystem;
 use std::cmp;
+use std::path::PathBuf;
 use x11::xlib::Window;
 
 #[cfg(feature = "reload")]
@@ -19,10 +20,20 @@ pub struct WorkspaceInfo {
     pub urgent: bool,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
+pub struct Project {
+    pub dir: PathBuf,
+    pub envs: Vec<(String, String)>,
+    // spawned the first time the workspace is visited while empty
+    pub startup: Vec<(String, Vec<String>)>,
+}
+
 pub struct WorkspaceConfig {
     pub tag: String,
     pub screen: usize,
     pub layout: Box<dyn Layout>,
+    pub project: Option<Project>,
 }
 
 pub enum MoveOp {
@@ -31,11 +42,87 @@ pub enum MoveOp {
     Swap,
 }
 
+// pointer driven move or resize of a floating window. Resize moves the given edges
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Drag {
+    Move,
+    Resize {
+        left: bool,
+        top: bool,
+        right: bool,
+        bottom: bool,
+    },
+}
+
+impl Drag {
+    pub fn apply(&self, rect: Rect, dx: i32, dy: i32) -> Rect {
+        let (mut x, mut y) = (rect.x as i32, rect.y as i32);
+        let (mut width, mut height) = (rect.width as i32, rect.height as i32);
+
+        match *self {
+            Drag::Move => {
+                x += dx;
+                y += dy;
+            }
+            Drag::Resize { left, top, right, bottom } => {
+                if left {
+                    let dx = cmp::min(dx, width - 1);
+                    x += dx;
+                    width -= dx;
+                } else if right {
+                    width = cmp::max(width + dx, 1);
+                }
+
+                if top {
+                    let dy = cmp::min(dy, height - 1);
+                    y += dy;
+                    height -= dy;
+                } else if bottom {
+                    height = cmp::max(height + dy, 1);
+                }
+            }
+        }
+
+        Rect {
+            x: cmp::max(x, 0) as u32,
+            y: cmp::max(y, 0) as u32,
+            width: width as u32,
+            height: height as u32,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub enum CycleMode {
+    All,
+    NonEmpty,
+    // skips workspaces that are visible on another screen
+    Hidden,
+    // only workspaces assigned to the current screen
+    Screen,
+}
+
 #[cfg_attr(feature = "reload", derive(Serialize, Deserialize))]
 pub struct Workspace {
     pub(crate) managed: Stack,
     pub(crate) floating: Stack,
     pub(crate) tag: String,
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) marks: Vec<(String, Window)>,
+    // minimized windows are kept out of the layout. The flag marks floating windows
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) minimized: Vec<(Window, bool)>,
+    // geometry of floating windows placed by the user instead of centred on the screen
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) floating_rects: Vec<(Window, Rect)>,
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) project: Option<Project>,
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) project_started: bool,
+    // all windows stay unmapped while the desktop is shown
+    #[cfg_attr(feature = "reload", serde(default))]
+    pub(crate) concealed: bool,
     pub index: usize,
     pub screen: usize,
     pub visible: bool,
@@ -48,6 +135,12 @@ impl Default for Workspace {
             managed: Stack::new(Some(Tall::new(1, 0.5, 0.05))),
             floating: Stack::new(None),
             tag: String::new(),
+            marks: Vec::new(),
+            minimized: Vec::new(),
+            floating_rects: Vec::new(),
+            project: None,
+            project_started: false,
+            concealed: false,
             index: 0,
             screen: 0,
             visible: false,
@@ -63,6 +156,7 @@ impl Workspace {
             .iter()
             .chain(self.managed.all_windows().iter())
             .copied()
+            .chain(self.minimized.iter().map(|&(w, _)| w))
             .collect()
     }
 
@@ -75,7 +169,7 @@ impl Workspace {
     }
 
     pub fn is_empty(&self) -> bool {
-        (self.floating.len() + self.managed.len()) == 0
+        (self.floating.len() + self.managed.len() + self.minimized.len()) == 0
     }
 
     pub fn send_layout_message(&mut self, xws: &XlibWindowSystem, msg: LayoutMsg) {
@@ -86,6 +180,10 @@ impl Workspace {
         &self.tag
     }
 
+    pub fn get_project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
     pub fn get_screen(&self) -> usize {
         self.screen
     }
@@ -98,6 +196,46 @@ impl Workspace {
         self.managed.contains(window)
     }
 
+    pub fn is_minimized(&self, window: Window) -> bool {
+        self.minimized.iter().any(|&(w, _)| w == window)
+    }
+
+    // in the order they got minimized
+    pub fn minimized_windows(&self) -> Vec<Window> {
+        self.minimized.iter().map(|&(w, _)| w).collect()
+    }
+
+    pub fn minimize(&mut self, window: Window) -> bool {
+        let is_floating = self.floating.contains(window);
+
+        if self.managed.contains(window) {
+            self.remove_managed(window);
+        } else if is_floating {
+            self.remove_floating(window);
+        } else {
+            return false;
+        }
+
+        self.minimized.push((window, is_floating));
+        true
+    }
+
+    pub fn restore(&mut self, xws: &XlibWindowSystem, window: Window) -> bool {
+        let Some(idx) = self.minimized.iter().position(|&(w, _)| w == window) else {
+            return false;
+        };
+
+        let (_, is_floating) = self.minimized.remove(idx);
+        if is_floating {
+            self.add_floating(window);
+        } else {
+            self.add_window(xws, window);
+        }
+
+        self.set_focus(window);
+        true
+    }
+
     pub fn is_urgent(&self) -> bool {
         self.managed.is_urgent() || self.floating.is_urgent()
     }
@@ -127,6 +265,40 @@ impl Workspace {
         }
     }
 
+    pub fn add_floating(&mut self, window: Window) {
+        debug!("Add Unmanaged: {:#x}", window);
+        self.floating.add_window(window);
+    }
+
+    // turns a tiled window into a floating one keeping its focus
+    pub fn float_window(&mut self, window: Window) -> bool {
+        if !self.managed.contains(window) {
+            return false;
+        }
+
+        let is_focused = self.managed.focused_window() == Some(window);
+        self.remove_managed(window);
+        self.add_floating(window);
+
+        if is_focused {
+            self.floating.focus_window(window);
+        }
+
+        true
+    }
+
+    pub fn set_floating_rect(&mut self, window: Window, rect: Rect) {
+        self.floating_rects.retain(|&(w, _)| w != window);
+        self.floating_rects.push((window, rect));
+    }
+
+    fn get_floating_rect(&self, window: Window) -> Option<Rect> {
+        self.floating_rects
+            .iter()
+            .find(|&&(w, _)| w == window)
+            .map(|&(_, rect)| rect)
+    }
+
     pub fn nest_layout(&mut self, layout: Box<dyn Layout>) {
         if self.managed.len() > 1 {
             self.managed.add_container(layout);
@@ -155,22 +327,21 @@ impl Workspace {
         }
     }
 
+    pub fn is_window_urgent(&self, window: Window) -> bool {
+        self.all_urgent().contains(&&window)
+    }
+
     fn remove_urgent_window(&mut self, window: Window) {
         if !self.managed.remove_urgent(window) {
             self.floating.remove_urgent(window);
         }
     }
 
-    fn remove_managed(&mut self, xws: &XlibWindowSystem, window: Window) {
+    fn remove_managed(&mut self, window: Window) {
         self.managed.remove(window);
-        xws.unmap_window(window);
     }
 
-    fn remove_floating(&mut self, xws: &XlibWindowSystem, window: Window) {
-        if !ewmh::is_window_sticky(xws, window) {
-            xws.unmap_window(window);
-        }
-
+    fn remove_floating(&mut self, window: Window) {
         self.floating.remove(window);
         self.floating.focus = if self.floating.nodes.is_empty() {
             None
@@ -179,13 +350,48 @@ impl Workspace {
         };
     }
 
-    pub fn remove_window(&mut self, xws: &XlibWindowSystem, window: Window) -> bool {
-        if self.managed.contains(window) {
+    pub fn get_marked(&self, mark: &str) -> Option<Window> {
+        self.marks
+            .iter()
+            .find(|(x, _)| x == mark)
+            .map(|&(_, w)| w)
+    }
+
+    pub fn add_mark(&mut self, mark: &str, window: Window) {
+        self.marks.push((mark.to_string(), window));
+    }
+
+    pub fn remove_mark(&mut self, mark: &str) {
+        self.marks.retain(|(x, _)| x != mark);
+    }
+
+    pub fn take_marks(&mut self, window: Window) -> Vec<String> {
+        let (marks, rest): (Vec<_>, Vec<_>) = self.marks
+            .drain(..)
+            .partition(|&(_, w)| w == window);
+
+        self.marks = rest;
+        marks.into_iter().map(|(x, _)| x).collect()
+    }
+
+    pub fn insert_after(&mut self, target: Window, window: Window) -> bool {
+        self.managed.insert_after(target, window) || self.floating.insert_after(target, window)
+    }
+
+    // removes the window from the stacks but leaves it mapped for copies on other workspaces
+    pub fn remove_copy(&mut self, window: Window) -> bool {
+        self.marks.retain(|&(_, w)| w != window);
+        self.floating_rects.retain(|&(w, _)| w != window);
+
+        if self.is_minimized(window) {
+            trace!("Remove Minimized: {:#x}", window);
+            self.minimized.retain(|&(w, _)| w != window);
+        } else if self.managed.contains(window) {
             trace!("Remove Managed: {:#x}", window);
-            self.remove_managed(xws, window);
+            self.remove_managed(window);
         } else if self.floating.contains(window) {
             trace!("Remove Unmanaged: {:#x}", window);
-            self.remove_floating(xws, window);
+            self.remove_floating(window);
         } else {
             return false;
         }
@@ -193,6 +399,48 @@ impl Workspace {
         true
     }
 
+    pub fn remove_window(&mut self, xws: &XlibWindowSystem, window: Window) -> bool {
+        if self.remove_copy(window) {
+            xws.hide_window(window);
+            true
+        } else {
+            false
+        }
+    }
+
+    // puts the new window in place of the old one keeping its position in the layout
+    pub fn replace_window(&mut self, window: Window, new_window: Window) -> bool {
+        for (w, _) in self.floating_rects.iter_mut().filter(|(w, _)| *w == window) {
+            *w = new_window;
+        }
+
+        self.managed.replace_window(window, new_window) || self.floating.replace_window(window, new_window)
+    }
+
+    // exchanges the places of two windows of this workspace. Urgency stays with the
+    // windows while the floating rects stay with the places
+    pub fn swap_windows(&mut self, window: Window, other: Window) {
+        self.managed.swap_windows(window, other);
+        self.floating.swap_windows(window, other);
+
+        for (w, _) in self.floating_rects.iter_mut() {
+            if *w == window {
+                *w = other;
+            } else if *w == other {
+                *w = window;
+            }
+        }
+    }
+
+    // changes the focused window of the stacks without focusing it on the X server
+    pub fn set_focus(&mut self, window: Window) -> bool {
+        if self.floating.contains(window) {
+            self.floating.focus_window(window)
+        } else {
+            self.managed.focus_window(window)
+        }
+    }
+
     pub fn focus_window(&mut self, xws: &XlibWindowSystem, window: Window) -> bool {
         if window == 0 || self.managed.focused_window() == Some(window) {
             return false;
@@ -275,7 +523,7 @@ impl Workspace {
 
         if let Some(window) = self
             .focused_window()
-            .or_else(|| self.all().first().copied())
+            .or_else(|| self.all().into_iter().find(|&w| !self.is_minimized(w)))
         {
             xws.focus_window(window);
         } else {
@@ -296,14 +544,15 @@ impl Workspace {
         );
     }
 
-    pub fn hide(&mut self, xws: &XlibWindowSystem) {
+    // windows in keep are copies shown on another workspace and stay mapped
+    pub fn hide(&mut self, xws: &XlibWindowSystem, keep: &[Window]) {
         self.visible = false;
 
         for &w in self
             .managed
             .all_windows()
             .iter()
-            .filter(|&w| Some(*w) != self.focused_window())
+            .filter(|&w| Some(*w) != self.focused_window() && !keep.contains(w))
         {
             xws.hide_window(w);
         }
@@ -312,20 +561,22 @@ impl Workspace {
             .floating
             .all_windows()
             .iter()
-            .filter(|&w| Some(*w) != self.focused_window())
+            .filter(|&w| Some(*w) != self.focused_window() && !keep.contains(w))
         {
             xws.hide_window(w);
         }
 
-        if let Some(w) = self.focused_window() {
-            xws.hide_window(w);
+        if let Some(w) = self.focused_window()
+            && !keep.contains(&w) {
+                xws.hide_window(w);
         }
     }
 
     pub fn show(&mut self, xws: &XlibWindowSystem) {
         self.visible = true;
 
-        for &w in self.managed.all_windows().iter() {
+        let hidden = self.managed.hidden_windows();
+        for &w in self.managed.all_windows().iter().filter(|w| !hidden.contains(w)) {
             xws.show_window(w);
             ewmh::set_wm_desktop(xws, w, self.index);
         }
@@ -336,12 +587,110 @@ impl Workspace {
         }
     }
 
+    // unmaps all windows without giving up the screen
+    pub fn conceal(&mut self, xws: &XlibWindowSystem) {
+        self.concealed = true;
+
+        for &w in self.managed.all_windows().iter().chain(self.floating.all_windows().iter()) {
+            xws.hide_window(w);
+        }
+    }
+
+    pub fn reveal(&mut self, xws: &XlibWindowSystem) {
+        self.concealed = false;
+        self.show(xws);
+    }
+
+    // shrinks the rect to a size accepted by the window and centres it in the original rect
+    fn apply_size_hints(xws: &XlibWindowSystem, rect: Rect, border_width: u32, window: Window) -> Rect {
+        let border = 2 * border_width;
+        let (width, height) = xws
+            .get_size_hints(window)
+            .apply(rect.width.saturating_sub(border), rect.height.saturating_sub(border));
+
+        let width = cmp::min(width + border, rect.width);
+        let height = cmp::min(height + border, rect.height);
+
+        Rect {
+            x: rect.x + (rect.width - width) / 2,
+            y: rect.y + (rect.height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    // maximized windows temporarily fill the tile area in the maximized directions
+    fn maximized_rect(xws: &XlibWindowSystem, rect: Rect, area: Rect, window: Window) -> Option<Rect> {
+        let vert = ewmh::has_wm_state(xws, window, "_NET_WM_STATE_MAXIMIZED_VERT");
+        let horz = ewmh::has_wm_state(xws, window, "_NET_WM_STATE_MAXIMIZED_HORZ");
+
+        if !vert && !horz {
+            return None;
+        }
+
+        Some(Rect {
+            x: if horz { area.x } else { rect.x },
+            y: if vert { area.y } else { rect.y },
+            width: if horz { area.width } else { rect.width },
+            height: if vert { area.height } else { rect.height },
+        })
+    }
+
+    // the windows from bottom to top in the order redraw stacks them
+    pub fn stacking_order(&self, xws: &XlibWindowSystem) -> Vec<Window> {
+        let has_state = |w: Window, state: &str| ewmh::has_wm_state(xws, w, state);
+        let is_raised = |w: Window| {
+            has_state(w, "_NET_WM_STATE_FULLSCREEN")
+                || has_state(w, "_NET_WM_STATE_MAXIMIZED_VERT")
+                || has_state(w, "_NET_WM_STATE_MAXIMIZED_HORZ")
+        };
+
+        let tiled = self.managed.all_windows();
+        let floating = self.floating.all_windows();
+        let (below, floating): (Vec<Window>, Vec<Window>) = floating
+            .into_iter()
+            .partition(|&w| has_state(w, "_NET_WM_STATE_BELOW"));
+        let (above, floating): (Vec<Window>, Vec<Window>) = floating
+            .into_iter()
+            .partition(|&w| has_state(w, "_NET_WM_STATE_ABOVE"));
+        let (raised, tiled): (Vec<Window>, Vec<Window>) = tiled
+            .into_iter()
+            .partition(|&w| is_raised(w));
+
+        self.minimized_windows()
+            .into_iter()
+            .chain(below)
+            .chain(tiled)
+            .chain(raised)
+            .chain(floating)
+            .chain(above)
+            .collect()
+    }
+
     pub fn redraw(&self, xws: &XlibWindowSystem, config: &Config, screens: &[Rect]) {
         trace!("Redraw workspace: {}", self.tag);
         let screen = screens[self.screen];
+        let work_area = xws.get_work_area(screen);
         let curr_focus = self.focused_window();
+        let rects = self.managed.apply_layout(screen, xws);
+
+        if self.visible && !self.concealed {
+            // unmap windows left out by the layout and map those that came back into view
+            for window in self.managed.all_windows() {
+                let is_shown = xws.is_mapped(window);
+
+                if rects.iter().any(|&(_, w)| w == window) {
+                    if !is_shown {
+                        xws.show_window(window);
+                        ewmh::set_wm_desktop(xws, window, self.index);
+                    }
+                } else if is_shown {
+                    xws.hide_window(window);
+                }
+            }
+        }
 
-        for (rect, window) in self.managed.apply_layout(screen, xws) {
+        for (rect, window) in rects {
             let is_fullscreen = ewmh::is_window_fullscreen(xws, window);
             let border_color = if Some(window) == curr_focus {
                 config.border_focus_color
@@ -361,6 +710,18 @@ impl Workspace {
                     window,
                 );
             } else {
+                let maximized = Self::maximized_rect(xws, rect, work_area, window);
+                let rect = maximized.unwrap_or(rect);
+                let rect = if config.respect_size_hints {
+                    Self::apply_size_hints(xws, rect, config.border_width, window)
+                } else {
+                    rect
+                };
+
+                if maximized.is_some() {
+                    xws.raise_window(window);
+                }
+
                 xws.setup_window(
                     rect.x,
                     rect.y,
@@ -373,20 +734,26 @@ impl Workspace {
             }
         }
 
-        for &window in self.floating.all_windows().iter() {
-            let mut rect = xws.get_geometry(window);
-            rect.width = cmp::min(screen.width, rect.width + (2 * config.border_width));
-            rect.height = cmp::min(screen.height, rect.height + (2 * config.border_width));
+        let floating = self.floating.all_windows();
+        for &window in floating.iter() {
+            let rect = self.get_floating_rect(window).unwrap_or_else(|| {
+                let mut rect = xws.get_geometry(window);
+                rect.width = cmp::min(screen.width, rect.width + (2 * config.border_width));
+                rect.height = cmp::min(screen.height, rect.height + (2 * config.border_width));
+                rect.x = screen.x + (screen.width - rect.width) / 2;
+                rect.y = screen.y + (screen.height - rect.height) / 2;
+                rect
+            });
+            let rect = Self::maximized_rect(xws, rect, work_area, window).unwrap_or(rect);
             let border_color = if Some(window) == curr_focus {
                 config.border_focus_color
             } else {
                 config.border_color
             };
 
-            xws.raise_window(window);
             xws.setup_window(
-                screen.x + (screen.width - rect.width) / 2,
-                screen.y + (screen.height - rect.height) / 2,
+                rect.x,
+                rect.y,
                 rect.width,
                 rect.height,
                 config.border_width,
@@ -395,6 +762,20 @@ impl Workspace {
             );
         }
 
+        // floating windows are stacked in layers above the tiled ones unless they are kept below
+        let is_above = |w: Window| ewmh::has_wm_state(xws, w, "_NET_WM_STATE_ABOVE");
+        let is_below = |w: Window| ewmh::has_wm_state(xws, w, "_NET_WM_STATE_BELOW");
+
+        for &window in floating.iter().filter(|&&w| !is_above(w) && !is_below(w)) {
+            xws.raise_window(window);
+        }
+        for &window in floating.iter().filter(|&&w| is_above(w)) {
+            xws.raise_window(window);
+        }
+        for &window in floating.iter().filter(|&&w| is_below(w)) {
+            xws.lower_window(window);
+        }
+
         for &window in self.all_urgent() {
             xws.set_window_border_color(window, config.border_urgent_color);
         }
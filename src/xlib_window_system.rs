@@ -7,10 +7,12 @@ use crate::keycode::{MOD_2, MOD_LOCK};
 use crate::layout::Rect;
 use crate::ewmh;
 use std::{cmp, env, ptr, str};
+use std::cell::{Cell, RefCell};
+use std::os::fd::RawFd;
+use std::time::Duration;
 use std::mem::MaybeUninit;
 use std::slice::from_raw_parts;
 use std::ffi::{CStr, CString};
-use std::time::{SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
 use self::libc::{c_void, c_char, c_uchar, c_int, c_uint, c_long, c_ulong};
 use self::XlibEvent::*;
@@ -37,16 +39,96 @@ pub enum XlibEvent {
     XKeyPress(Window, u8, String),
     XButtonPress(Window),
     WMClose,
+    Timeout,
+    // the extra fd passed to get_event can be read
+    Readable,
     Ignored,
 }
 
 pub struct SizeHint {
     pub min: Option<(u32, u32)>,
     pub max: Option<(u32, u32)>,
+    pub base: Option<(u32, u32)>,
+    pub inc: Option<(u32, u32)>,
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+}
+
+impl SizeHint {
+    // negative values sent by broken clients count as unset
+    fn clamp(x: c_int, y: c_int) -> (u32, u32) {
+        (x.max(0) as u32, y.max(0) as u32)
+    }
+
+    // shrinks the given client size to the closest size allowed by the hints.
+    // The result never exceeds the given size, even if the minimum size would
+    pub fn apply(&self, width: u32, height: u32) -> (u32, u32) {
+        // ICCCM: the base size defaults to the minimum size and vice versa
+        let base = self.base.or(self.min).unwrap_or((0, 0));
+        let min = self.min.or(self.base).unwrap_or((0, 0));
+        let base_is_min = self.base.is_none() || self.min.is_none() || base == min;
+
+        let mut w = width as f64;
+        let mut h = height as f64;
+
+        // the aspect ratio excludes the base size unless it is also the minimum size
+        if !base_is_min {
+            w -= base.0 as f64;
+            h -= base.1 as f64;
+        }
+
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.aspect
+            && min_x > 0 && min_y > 0 && max_x > 0 && max_y > 0 && w > 0.0 && h > 0.0
+        {
+            let min_aspect = min_x as f64 / min_y as f64;
+            let max_aspect = max_x as f64 / max_y as f64;
+
+            if w / h > max_aspect {
+                w = (h * max_aspect).round();
+            } else if w / h < min_aspect {
+                h = (w / min_aspect).round();
+            }
+        }
+
+        if base_is_min {
+            w -= base.0 as f64;
+            h -= base.1 as f64;
+        }
+
+        let mut w = w.max(0.0) as u32;
+        let mut h = h.max(0.0) as u32;
+
+        if let Some((inc_w, inc_h)) = self.inc {
+            if inc_w > 0 {
+                w -= w % inc_w;
+            }
+            if inc_h > 0 {
+                h -= h % inc_h;
+            }
+        }
+
+        w = cmp::max(w.saturating_add(base.0), min.0);
+        h = cmp::max(h.saturating_add(base.1), min.1);
+
+        if let Some((max_w, max_h)) = self.max {
+            if max_w > 0 {
+                w = cmp::min(w, max_w);
+            }
+            if max_h > 0 {
+                h = cmp::min(h, max_h);
+            }
+        }
+
+        (cmp::min(w, width), cmp::min(h, height))
+    }
 }
 
 pub struct Strut(pub u32, pub u32, pub u32, pub u32);
 
+// ICCCM WM_STATE values
+pub const WM_STATE_WITHDRAWN: u64 = 0;
+pub const WM_STATE_NORMAL: u64 = 1;
+pub const WM_STATE_ICONIC: u64 = 3;
+
 pub struct WindowChanges {
     pub x: u32,
     pub y: u32,
@@ -62,6 +144,10 @@ pub struct XlibWindowSystem {
     root: Window,
     event: *mut c_void,
     atoms: HashMap<&'static str, Atom>,
+    // server time of the latest event, ICCCM forbids CurrentTime in WM_PROTOCOLS messages
+    last_time: Cell<Time>,
+    // _NET_WM_STATE by window, read on every redraw and restack. Dropped whenever it changes
+    wm_states: RefCell<HashMap<Window, Vec<Atom>>>,
 }
 
 impl XlibWindowSystem {
@@ -81,6 +167,8 @@ impl XlibWindowSystem {
                 root,
                 event: libc::malloc(256),
                 atoms: HashMap::new(),
+                last_time: Cell::new(CurrentTime),
+                wm_states: RefCell::new(HashMap::new()),
             }
         }
     }
@@ -95,6 +183,32 @@ impl XlibWindowSystem {
 
         self.cache_atoms(&["WM_DELETE_WINDOW", "WM_HINTS", "WM_PROTOCOLS", "WM_STATE", "WM_TAKE_FOCUS", "UTF8_STRING"]);
         ewmh::init_ewmh(self);
+
+        // the messages sent before the first timestamped event need a valid time as well
+        if let Some(&check) = self.get_property(self.root, "_NET_SUPPORTING_WM_CHECK").as_ref().and_then(|x| x.first()) {
+            self.last_time.set(self.get_server_time(check));
+        }
+    }
+
+    // ICCCM 2.1: a zero length append generates a PropertyNotify carrying the server time
+    fn get_server_time(&self, window: Window) -> Time {
+        unsafe {
+            XSelectInput(self.display, window, PropertyChangeMask);
+            XChangeProperty(self.display,
+                            window,
+                            self.get_atom("_NET_WM_NAME"),
+                            self.get_atom("UTF8_STRING"),
+                            8,
+                            PropModeAppend,
+                            ptr::null(),
+                            0);
+
+            let mut event: XEvent = std::mem::zeroed();
+            XWindowEvent(self.display, window, PropertyChangeMask, &mut event);
+            XSelectInput(self.display, window, NoEventMask);
+
+            event.property.time
+        }
     }
 
     pub fn close(&mut self) {
@@ -119,6 +233,7 @@ impl XlibWindowSystem {
                         window: Window) {
         self.set_window_border_width(window, border_width);
         self.set_window_border_color(window, border_color);
+        ewmh::set_frame_extents(self, window, border_width);
 
         self.move_resize_window(window,
                                 x,
@@ -167,6 +282,46 @@ impl XlibWindowSystem {
         }
     }
 
+    // reads a list of null separated strings like _NET_DESKTOP_NAMES
+    pub fn get_string_list<A: IntoAtom>(&self, window: Window, atom: A) -> Vec<String> {
+        unsafe {
+            let mut ret_type: c_ulong = 0;
+            let mut ret_format: c_int = 0;
+            let mut ret_nitems: c_ulong = 0;
+            let mut ret_bytes_after: c_ulong = 0;
+            let mut ret_ptr = MaybeUninit::<*mut c_uchar>::uninit();
+
+            if XGetWindowProperty(self.display,
+                                  window,
+                                  atom.into(self),
+                                  0,
+                                  0xFFFF_FFFF,
+                                  0,
+                                  0,
+                                  &mut ret_type,
+                                  &mut ret_format,
+                                  &mut ret_nitems,
+                                  &mut ret_bytes_after,
+                                  ret_ptr.as_mut_ptr()) == 0 && ret_format == 8 {
+                let ret_ptr = ret_ptr.assume_init();
+                let bytes = from_raw_parts(ret_ptr as *const u8, ret_nitems as usize).to_vec();
+                XFree(ret_ptr as *mut c_void);
+
+                if bytes.is_empty() {
+                    return Vec::new();
+                }
+
+                bytes.strip_suffix(&[0])
+                    .unwrap_or(&bytes)
+                    .split(|&x| x == 0)
+                    .map(|x| String::from_utf8_lossy(x).into_owned())
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
     pub fn cache_atoms(&mut self, atoms_str: &[&'static str]) {
         let atoms = self.create_atoms(atoms_str);
 
@@ -263,6 +418,11 @@ impl XlibWindowSystem {
         }
     }
 
+    // the actual map state on the server, WM_STATE is not updated by every unmap
+    pub fn is_mapped(&self, window: Window) -> bool {
+        self.get_window_attributes(window).map_state != IsUnmapped
+    }
+
     // TODO: cache result and split into computation and getter functions.
     // Struts rarely change and dont have to be computed on every redraw (see strut layout)
     pub fn compute_struts(&self, screen: Rect) -> Strut {
@@ -299,21 +459,33 @@ impl XlibWindowSystem {
             })
     }
 
-    pub fn change_property<T: Into<u64>, A: IntoAtom>(&self,
+    // the part of the screen not reserved by docks
+    pub fn get_work_area(&self, screen: Rect) -> Rect {
+        let strut = self.compute_struts(screen);
+
+        Rect {
+            x: screen.x + strut.0,
+            y: screen.y + strut.2,
+            width: screen.width - (strut.0 + strut.1),
+            height: screen.height - (strut.2 + strut.3),
+        }
+    }
+
+    pub fn change_property<T: PropertyData, A: IntoAtom>(&self,
                        window: Window,
                        atom: &str,
                        atom_type: A,
                        mode: c_int,
                        data: &[T])
     {
+        self.forget_wm_state(window, self.get_atom(atom));
+
         unsafe {
             XChangeProperty(self.display,
                             window,
                             self.get_atom(atom),
                             atom_type.into(self),
-                            // Xlib requires char for format 8, short for 16 and 32 for long
-                            // skipping over int32 for some reason
-                            std::cmp::min(32, std::mem::size_of::<T>() as i32 * 8),
+                            T::FORMAT,
                             mode,
                             data.as_ptr().cast::<u8>(),
                             data.len() as i32);
@@ -339,8 +511,28 @@ impl XlibWindowSystem {
     }
 
     pub fn delete_property<A: IntoAtom>(&self, window: Window, atom: A) {
+        let atom = atom.into(self);
+        self.forget_wm_state(window, atom);
+
         unsafe {
-            XDeleteProperty(self.display, window, atom.into(self));
+            XDeleteProperty(self.display, window, atom);
+        }
+    }
+
+    // the cached _NET_WM_STATE of the window
+    pub fn get_wm_state(&self, window: Window) -> Vec<Atom> {
+        if let Some(atoms) = self.wm_states.borrow().get(&window) {
+            return atoms.clone();
+        }
+
+        let atoms = self.get_property(window, "_NET_WM_STATE").unwrap_or_default();
+        self.wm_states.borrow_mut().insert(window, atoms.clone());
+        atoms
+    }
+
+    fn forget_wm_state(&self, window: Window, atom: Atom) {
+        if atom == self.get_atom("_NET_WM_STATE") {
+            self.wm_states.borrow_mut().remove(&window);
         }
     }
 
@@ -389,24 +581,36 @@ impl XlibWindowSystem {
         }
     }
 
+    pub fn set_window_state(&self, window: Window, state: u64) {
+        self.change_property(window, "WM_STATE", "WM_STATE", PropModeReplace, &[state, 0]);
+    }
+
     pub fn show_window(&self, window: Window) {
         unsafe {
-            self.change_property(window, "WM_STATE", "WM_STATE", PropModeReplace, &[1u64, 0]);
+            self.set_window_state(window, WM_STATE_NORMAL);
             XMapWindow(self.display, window);
         }
     }
 
+    // unmaps a window that stays managed
     pub fn hide_window(&self, window: Window) {
         unsafe {
             XSelectInput(self.display, window, 0x0040_0010 | FocusChangeMask);
             XUnmapWindow(self.display, window);
             XSelectInput(self.display, window, 0x0042_0010 | FocusChangeMask);
 
-            self.change_property(window, "WM_STATE", "WM_STATE", PropModeReplace, &[0u64, 0]);
+            self.set_window_state(window, WM_STATE_ICONIC);
             self.delete_property(window, "_NET_WM_DESKTOP");
         }
     }
 
+    // the client unmapped the window and it is no longer managed
+    pub fn withdraw_window(&self, window: Window) {
+        self.set_window_state(window, WM_STATE_WITHDRAWN);
+        self.delete_property(window, "_NET_WM_DESKTOP");
+        self.delete_property(window, "_NET_WM_STATE");
+    }
+
     pub fn lower_window(&self, window: Window) {
         unsafe {
             XLowerWindow(self.display, window);
@@ -431,44 +635,69 @@ impl XlibWindowSystem {
         }
     }
 
+    // ICCCM 4.1.7: passive and locally active clients get the input focus assigned,
+    // globally active clients (input = false) are asked to take it with WM_TAKE_FOCUS
     pub fn focus_window(&self, window: Window) {
-        let input_hint = self.get_wm_hints(window).map(|x| x.input != 0).unwrap_or(true);
+        let input_hint = self.has_input_hint(window);
         let takes_focus = self.has_protocol(window, "WM_TAKE_FOCUS");
 
-        if input_hint {
-            unsafe {
-                XSetInputFocus(self.display, window, 1, 0);
-                self.skip_enter_events();
-            }
-
-            ewmh::set_active_window(self, window);
-            let state = self.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
-            self.send_client_message(window, "_NET_WM_STATE", [0, state, 0, 0, 0]);
-        } else if takes_focus {
-            let time = SystemTime::now().duration_since(UNIX_EPOCH)
-                .map(|x| x.as_secs())
-                .unwrap_or(0);
-
-            let atom = self.get_atom("WM_TAKE_FOCUS");
-            let mut event = XClientMessageEvent {
-                type_: ClientMessage,
-                serial: 0,
-                send_event: 0,
-                display: ptr::null_mut(),
-                window,
-                message_type: self.get_atom("WM_PROTOCOLS") as c_ulong,
-                format: 32,
-                data: ClientMessageData::from([atom, time, 0, 0, 0]),
-            };
+        // no input model
+        if !input_hint && !takes_focus {
+            return;
+        }
 
-            let event_ptr: *mut XClientMessageEvent = &mut event;
+        if input_hint {
             unsafe {
-                XSendEvent(self.display, window, 0, NoEventMask, event_ptr as *mut XEvent);
+                XSetInputFocus(self.display, window, RevertToPointerRoot, CurrentTime);
             }
-            let state = self.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
+        }
+
+        if takes_focus {
+            self.send_protocol(window, "WM_TAKE_FOCUS");
+        }
+
+        self.skip_enter_events();
+
+        ewmh::set_active_window(self, window);
+        let state = self.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
+        self.send_client_message(window, "_NET_WM_STATE", [0, state, 0, 0, 0]);
+    }
+
+    fn has_input_hint(&self, window: Window) -> bool {
+        self.get_wm_hints(window).map(|x| x.input != 0).unwrap_or(true)
+    }
 
-            ewmh::set_active_window(self, window);
-            self.send_client_message(window, "_NET_WM_STATE", [0, state, 0, 0, 0]);
+    // returns false if the client does not support _NET_WM_PING
+    pub fn ping_window(&self, window: Window) -> bool {
+        if !self.has_protocol(window, "_NET_WM_PING") {
+            return false;
+        }
+
+        self.send_protocol(window, "_NET_WM_PING");
+        true
+    }
+
+    // false for windows following the no input model
+    pub fn accepts_focus(&self, window: Window) -> bool {
+        self.has_input_hint(window) || self.has_protocol(window, "WM_TAKE_FOCUS")
+    }
+
+    fn send_protocol(&self, window: Window, protocol: &str) {
+        let mut event = XClientMessageEvent {
+            type_: ClientMessage,
+            serial: 0,
+            send_event: 0,
+            display: ptr::null_mut(),
+            window,
+            message_type: self.get_atom("WM_PROTOCOLS") as c_ulong,
+            format: 32,
+            // _NET_WM_PING expects the window in the third field, the other protocols ignore it
+            data: ClientMessageData::from([self.get_atom(protocol), self.last_time.get(), window, 0, 0]),
+        };
+
+        let event_ptr: *mut XClientMessageEvent = &mut event;
+        unsafe {
+            XSendEvent(self.display, window, 0, NoEventMask, event_ptr as *mut XEvent);
         }
     }
 
@@ -510,30 +739,18 @@ impl XlibWindowSystem {
             return;
         }
 
-        unsafe {
-            if self.has_protocol(window, "WM_DELETE_WINDOW") {
-                let time = SystemTime::now().duration_since(UNIX_EPOCH)
-                    .map(|x| x.as_secs())
-                    .unwrap_or(0);
-                let delete_atom = self.get_atom("WM_DELETE_WINDOW");
-
-                let mut event = XClientMessageEvent {
-                    type_: ClientMessage,
-                    serial: 0,
-                    send_event: 0,
-                    display: ptr::null_mut(),
-                    window,
-                    message_type: self.get_atom("WM_PROTOCOLS") as c_ulong,
-                    format: 32,
-                    data: ClientMessageData::from([delete_atom, time, 0, 0, 0]),
-                };
+        if self.has_protocol(window, "WM_DELETE_WINDOW") {
+            self.send_protocol(window, "WM_DELETE_WINDOW");
+        } else {
+            self.kill_client(window);
+        }
+    }
 
-                let event_ptr: *mut XClientMessageEvent = &mut event;
-                XSendEvent(self.display, window, 0, NoEventMask, event_ptr as *mut XEvent);
-            } else {
-                XKillClient(self.display, window);
-                XSync(self.display, 0);
-            }
+    // closes the connection of the client owning the window
+    pub fn kill_client(&self, window: Window) {
+        unsafe {
+            XKillClient(self.display, window);
+            XSync(self.display, 0);
         }
     }
 
@@ -674,28 +891,14 @@ impl XlibWindowSystem {
         }
     }
 
+    // windows with a fixed size can not be tiled. Everything else depends on the window type
     pub fn is_floating_window(&self, window: Window) -> bool {
-        if self.transient_for(window).is_some() {
-            return true;
-        }
-
         let hints = self.get_size_hints(window);
         let min = hints.min;
         let max = hints.max;
 
-        if min.is_some() && max.is_some() && min.unwrap().0 == max.unwrap().0 &&
-           min.unwrap().1 == max.unwrap().1 {
-            return true;
-        }
-
-        if let Some(property) = self.get_property(window, self.get_atom("_NET_WM_WINDOW_TYPE")) {
-            let dialog = self.get_atom("_NET_WM_WINDOW_TYPE_DIALOG");
-            let splash = self.get_atom("_NET_WM_WINDOW_TYPE_SPLASH");
-
-            property.iter().any(|&x| x == dialog || x == splash)
-        } else {
-            false
-        }
+        min.is_some() && max.is_some() && min.unwrap().0 == max.unwrap().0 &&
+            min.unwrap().1 == max.unwrap().1
     }
 
     pub fn transient_for(&self, window: Window) -> Option<Window> {
@@ -718,19 +921,42 @@ impl XlibWindowSystem {
 
             let size_hint = size_hint.assume_init();
             let min = if (size_hint.flags & PMinSize) != 0 {
-                Some((size_hint.min_width as u32, size_hint.min_height as u32))
+                Some(SizeHint::clamp(size_hint.min_width, size_hint.min_height))
             } else {
                 None
             };
 
             let max = if (size_hint.flags & PMaxSize) != 0 {
-                Some((size_hint.max_width as u32, size_hint.max_height as u32))
+                Some(SizeHint::clamp(size_hint.max_width, size_hint.max_height))
+            } else {
+                None
+            };
+
+            let base = if (size_hint.flags & PBaseSize) != 0 {
+                Some(SizeHint::clamp(size_hint.base_width, size_hint.base_height))
             } else {
                 None
             };
+
+            let inc = if (size_hint.flags & PResizeInc) != 0 {
+                Some(SizeHint::clamp(size_hint.width_inc, size_hint.height_inc))
+            } else {
+                None
+            };
+
+            let aspect = if (size_hint.flags & PAspect) != 0 {
+                Some((SizeHint::clamp(size_hint.min_aspect.x, size_hint.min_aspect.y),
+                      SizeHint::clamp(size_hint.max_aspect.x, size_hint.max_aspect.y)))
+            } else {
+                None
+            };
+
             SizeHint {
                 min,
                 max,
+                base,
+                inc,
+                aspect,
             }
         }
     }
@@ -744,6 +970,13 @@ impl XlibWindowSystem {
         }
     }
 
+    // ICCCM 4.1.3.1: the client asks to be mapped in the iconic state
+    pub fn starts_iconic(&self, window: Window) -> bool {
+        self.get_wm_hints(window)
+            .map(|x| x.flags & StateHint != 0 && x.initial_state as u64 == WM_STATE_ICONIC)
+            .unwrap_or(false)
+    }
+
     pub fn is_urgent(&self, window: Window) -> bool {
         self.get_wm_hints(window)
             .map(|x| x.flags & XUrgencyHint != 0)
@@ -838,6 +1071,57 @@ impl XlibWindowSystem {
         }
     }
 
+    // grabs the pointer and reports its offset to the starting position until all buttons are released.
+    // Does nothing if no button is held down anymore
+    pub fn track_pointer<F: FnMut(i32, i32)>(&self, mut on_motion: F) {
+        unsafe {
+            let mut root_w = MaybeUninit::uninit();
+            let mut child_w = MaybeUninit::uninit();
+            let mut root_x = MaybeUninit::uninit();
+            let mut root_y = MaybeUninit::uninit();
+            let mut win_x = MaybeUninit::uninit();
+            let mut win_y = MaybeUninit::uninit();
+            let mut mask = MaybeUninit::uninit();
+
+            let ret = XQueryPointer(
+                self.display,
+                self.root,
+                root_w.as_mut_ptr() as *mut Window,
+                child_w.as_mut_ptr() as *mut Window,
+                root_x.as_mut_ptr(),
+                root_y.as_mut_ptr(),
+                win_x.as_mut_ptr(),
+                win_y.as_mut_ptr(),
+                mask.as_mut_ptr());
+
+            let buttons = Button1Mask | Button2Mask | Button3Mask | Button4Mask | Button5Mask;
+            if ret != 1 || mask.assume_init() & buttons == 0 {
+                return;
+            }
+
+            let (start_x, start_y) = (root_x.assume_init(), root_y.assume_init());
+            let event_mask = ButtonReleaseMask | PointerMotionMask;
+
+            if XGrabPointer(self.display, self.root, 0, event_mask as c_uint, GrabModeAsync, GrabModeAsync, 0, 0, CurrentTime) != GrabSuccess {
+                return;
+            }
+
+            let mut event: XEvent = MaybeUninit::zeroed().assume_init();
+            loop {
+                XMaskEvent(self.display, event_mask, &mut event);
+
+                match event.get_type() {
+                    MotionNotify => on_motion(event.motion.x_root - start_x, event.motion.y_root - start_y),
+                    ButtonRelease => break,
+                    _ => (),
+                }
+            }
+
+            XUngrabPointer(self.display, CurrentTime);
+            XSync(self.display, 0);
+        }
+    }
+
     pub fn request_window_events(&self, window: Window) {
         unsafe {
             self.grab_button(window);
@@ -850,12 +1134,39 @@ impl XlibWindowSystem {
     }
 
     #[allow(clippy::nonminimal_bool)]
-    pub fn get_event(&self) -> XlibEvent {
+    // waits at most the given time for the next event
+    // waits for the next X event, the timeout or until `fd` can be read
+    pub fn get_event(&self, timeout: Option<Duration>, fd: Option<RawFd>) -> XlibEvent {
         if self.display.is_null() {
             return WMClose;
         }
 
         unsafe {
+            if (timeout.is_some() || fd.is_some()) && XPending(self.display) == 0 {
+                // negative fds are ignored by poll
+                let mut fds = [
+                    libc::pollfd {
+                        fd: XConnectionNumber(self.display),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    },
+                    libc::pollfd {
+                        fd: fd.unwrap_or(-1),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    },
+                ];
+                let timeout = timeout.map_or(-1, |x| x.as_millis().min(c_int::MAX as u128) as c_int);
+
+                if libc::poll(fds.as_mut_ptr(), 2, timeout) <= 0 {
+                    return Timeout;
+                }
+
+                if fds[1].revents != 0 {
+                    return Readable;
+                }
+            }
+
             XNextEvent(self.display, self.event as *mut XEvent);
         }
 
@@ -869,6 +1180,9 @@ impl XlibWindowSystem {
                 let evt: &XMapRequestEvent = self.cast_event_to();
                 trace!("MapRequest {:?}", evt);
 
+                // property changes of windows that were never mapped were not selected
+                self.wm_states.borrow_mut().remove(&evt.window);
+
                 // Some docks rely entirely on the EWMH window type and do not set redirect
                 // override to prevent the WM from reparenting it
                 let dock_type = self.get_atom("_NET_WM_WINDOW_TYPE_DOCK");
@@ -937,6 +1251,7 @@ impl XlibWindowSystem {
             }
             DestroyNotify => {
                 let evt: &XDestroyWindowEvent = self.cast_event_to();
+                self.wm_states.borrow_mut().remove(&evt.window);
                 XDestroy(evt.window)
             }
             UnmapNotify => {
@@ -945,10 +1260,13 @@ impl XlibWindowSystem {
             }
             PropertyNotify => {
                 let evt: &XPropertyEvent = self.cast_event_to();
+                self.last_time.set(evt.time);
+                self.forget_wm_state(evt.window, evt.atom);
                 XPropertyNotify(evt.window, evt.atom, evt.state == 0)
             }
             EnterNotify => {
                 let evt: &XEnterWindowEvent = self.cast_event_to();
+                self.last_time.set(evt.time);
                 if evt.detail != 2 {
                     XEnterNotify(evt.window, false, evt.x as u32, evt.y as u32)
                 } else if evt.detail == 2 && evt.window == self.root {
@@ -978,6 +1296,7 @@ impl XlibWindowSystem {
             }
             ButtonPress => {
                 let evt: &XButtonPressedEvent = self.cast_event_to();
+                self.last_time.set(evt.time);
                 unsafe {
                     XAllowEvents(self.display, 2, 0);
                 }
@@ -986,6 +1305,7 @@ impl XlibWindowSystem {
             }
             KeyPress => {
                 let evt: &XKeyPressedEvent = self.cast_event_to();
+                self.last_time.set(evt.time);
                 XKeyPress(evt.window,
                           evt.state as u8,
                           self.keycode_to_string(evt.keycode))
@@ -1001,6 +1321,32 @@ impl Default for XlibWindowSystem {
     }
 }
 
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for super::c_ulong {}
+}
+
+// Xlib requires char for format 8, short for 16 and long for 32 skipping over
+// int32, so other types would be read past their end
+pub trait PropertyData: sealed::Sealed {
+    const FORMAT: c_int;
+}
+
+impl PropertyData for u8 {
+    const FORMAT: c_int = 8;
+}
+
+impl PropertyData for u16 {
+    const FORMAT: c_int = 16;
+}
+
+impl PropertyData for c_ulong {
+    const FORMAT: c_int = 32;
+}
+
 pub trait IntoAtom {
     fn into(self, xws: &XlibWindowSystem) -> Atom;
 }
@@ -1016,3 +1362,66 @@ impl IntoAtom for &str {
         xws.get_atom(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_hints() -> SizeHint {
+        SizeHint { min: None, max: None, base: None, inc: None, aspect: None }
+    }
+
+    #[test]
+    fn apply_without_hints() {
+        assert_eq!(no_hints().apply(100, 50), (100, 50));
+    }
+
+    #[test]
+    fn apply_min_max() {
+        let hints = SizeHint { min: Some((10, 10)), max: Some((50, 40)), ..no_hints() };
+
+        assert_eq!(hints.apply(100, 100), (50, 40));
+        // the result never exceeds the given size
+        assert_eq!(hints.apply(5, 5), (5, 5));
+    }
+
+    #[test]
+    fn apply_increment() {
+        let hints = SizeHint { base: Some((4, 6)), inc: Some((10, 20)), ..no_hints() };
+
+        assert_eq!(hints.apply(100, 100), (94, 86));
+        assert_eq!(SizeHint { inc: Some((0, 0)), ..no_hints() }.apply(99, 99), (99, 99));
+    }
+
+    #[test]
+    fn apply_aspect() {
+        let hints = SizeHint { aspect: Some(((1, 1), (1, 1))), ..no_hints() };
+
+        assert_eq!(hints.apply(200, 100), (100, 100));
+        assert_eq!(hints.apply(100, 200), (100, 100));
+    }
+
+    #[test]
+    fn apply_base_excluded_from_aspect() {
+        let hints = SizeHint {
+            base: Some((10, 10)),
+            min: Some((20, 20)),
+            aspect: Some(((2, 1), (2, 1))),
+            ..no_hints()
+        };
+
+        assert_eq!(hints.apply(110, 110), (110, 60));
+    }
+
+    #[test]
+    fn apply_huge_base() {
+        let hints = SizeHint { base: Some((u32::MAX, u32::MAX)), ..no_hints() };
+
+        assert_eq!(hints.apply(100, 100), (100, 100));
+    }
+
+    #[test]
+    fn clamp_negative_hints() {
+        assert_eq!(SizeHint::clamp(-5, 7), (0, 7));
+    }
+}
@@ -67,11 +67,14 @@ fn run() -> Result<()> {
         .context("failed to create initial wm state")?;
 
     state.rescreen(xws, &config);
+    state.start_project(state.get_ws_index());
 
     ewmh::set_current_desktop(xws, state.get_ws_index());
     ewmh::set_number_of_desktops(xws, state.ws_count());
     ewmh::set_desktop_names(xws, state.all_ws());
     ewmh::set_desktop_viewport(xws, state.all_ws());
+    ewmh::set_workarea(xws, state.all_ws());
+    ewmh::set_showing_desktop(xws, state.is_showing_desktop());
 
     info!("entering event loop");
     run_event_loop(config, xws, state)
@@ -85,10 +88,19 @@ fn run_event_loop(config: Config, xws: &mut XlibWindowSystem, mut state: WmState
         .context("failed to start statusbar")?;
 
     loop {
-        match xws.get_event() {
+        if state.expire_pings(config.ping_timeout)
+            && let Some(ref mut handle) = bar_handle
+            && let Err(e) = config.statusbar.as_ref().unwrap().update(handle, xws, &state) {
+                error!("{}", e.context("failed to update statusbar"));
+        }
+
+        match xws.get_event(state.ping_deadline(config.ping_timeout), state.menu_fd()) {
             XMapRequest(window, is_sticky) => {
                 trace!("XMapRequest: {:#x} {}", window, is_sticky);
-                if !state.contains(window) {
+                if state.is_minimized(window) {
+                    // the client deiconified itself
+                    state.focus_window(xws, &config, window, false);
+                } else if !state.contains(window) {
                     let mut is_hooked = false;
                     if let Some(class) = xws.get_class_name(window) {
                         for hook in config.manage_hooks.iter() {
@@ -100,10 +112,19 @@ fn run_event_loop(config: Config, xws: &mut XlibWindowSystem, mut state: WmState
                     }
 
                     if !is_hooked {
-                        state.add_window(None, xws, &config, window);
+                        let index = state.take_spawn_target(xws, window);
+                        state.add_window(index, xws, &config, window);
                     }
 
-                    state.focus_window(xws, &config, window, false);
+                    if is_sticky {
+                        state.copy_window_to_all(xws, &config, window);
+                    }
+
+                    if xws.starts_iconic(window) {
+                        state.minimize_window(xws, &config, window);
+                    } else {
+                        state.focus_window(xws, &config, window, false);
+                    }
                 }
             }
             XDestroy(window) => {
@@ -111,23 +132,30 @@ fn run_event_loop(config: Config, xws: &mut XlibWindowSystem, mut state: WmState
                 if state.contains(window) {
                     state.remove_window(xws, &config, window);
                 }
+                state.forget_window(window);
             }
             XUnmapNotify(window, send) => {
                 trace!("XUnmapNotify: {:#x} {}", window, send);
                 if send && state.contains(window) {
                     state.remove_window(xws, &config, window);
+                    state.forget_window(window);
+                    xws.withdraw_window(window);
                 } else if state.try_remove_unmanaged(window) {
+                    xws.withdraw_window(window);
                     state.redraw(xws, &config);
                 }
             }
             XPropertyNotify(window, atom, _is_new_value) => {
                 if atom == xws.get_atom("WM_HINTS") {
-                    if let Some(ws) = state.get_parent_mut(window) {
-                        ws.set_urgency(xws.is_urgent(window), window);
-                    }
+                    state.set_urgency(xws.is_urgent(window), window);
                 } else if atom == xws.get_atom("_NET_WM_STRUT_PARTIAL") {
                     state.redraw(xws, &config);
-                } else if window == xws.get_root_window()
+                    ewmh::set_workarea(xws, state.all_ws());
+                } else if window == xws.get_root_window() && atom == xws.get_atom("_NET_DESKTOP_NAMES") {
+                    state.sync_desktop_names(xws);
+                }
+
+                if window == xws.get_root_window()
                     && (atom == xws.get_atom("_NET_CURRENT_DESKTOP")
                         || atom == xws.get_atom("_NET_NUMBER_OF_DESKTOPS")
                         || atom == xws.get_atom("_NET_DESKTOP_NAMES")
@@ -164,6 +192,7 @@ fn run_event_loop(config: Config, xws: &mut XlibWindowSystem, mut state: WmState
             XFocusIn(window) => {
                 trace!("Focus event by: {:#x}", window);
                 if let Some(idx) = state.find_window(window) {
+                    state.record_focus(window);
                     let screens = state.get_screens();
                     if let Some(workspace) = state.get_ws(idx) {
                         workspace.redraw(xws, &config, screens);
@@ -186,6 +215,12 @@ fn run_event_loop(config: Config, xws: &mut XlibWindowSystem, mut state: WmState
                     }
                 }
             }
+            Readable => {
+                trace!("menu finished");
+                commands::finish_menu(xws, &mut state, &config)
+                    .map_err(|e| error!("{}", utils::concat_error_chain(&e)))
+                    .ok();
+            }
             WMClose => return Ok(()),
             _ => {}
         }